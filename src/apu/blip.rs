@@ -0,0 +1,100 @@
+use std::f64::consts::PI;
+
+const PHASES: usize = 32;           /* Sub-sample positions the step kernel is tabulated for */
+const KERNEL_WIDTH: usize = 16;     /* Output samples each step is spread across */
+const CUTOFF: f64 = 0.45;           /* Fraction of the output rate kept below Nyquist */
+
+/* Band-limited resampler in the style of blip_buf. Instead of sampling the APU's output
+ * directly, every change in level is added as a band-limited step at its exact position
+ * between output samples; integrating the steps gives the output without aliasing. */
+pub struct BlipBuffer {
+    factor: f64,                    /* Output samples per input clock */
+    offset: f64,                    /* Output position of clock 0 of the current frame */
+    buffer: Vec<f32>,               /* Pending deltas, one per output sample */
+    integrator: f32,
+    kernel: Vec<[f32; KERNEL_WIDTH]>,
+}
+
+impl BlipBuffer {
+    /* `capacity` is the most output samples a single frame can produce */
+    pub fn new(clock_rate: f64, sample_rate: f64, capacity: usize) -> Self {
+        BlipBuffer {
+            factor: sample_rate / clock_rate,
+            offset: 0.0,
+            buffer: vec![0.0; capacity + KERNEL_WIDTH],
+            integrator: 0.0,
+            kernel: BlipBuffer::build_kernel(),
+        }
+    }
+
+    /* Windowed-sinc impulses, each normalised so a step of 1.0 integrates to exactly 1.0 */
+    fn build_kernel() -> Vec<[f32; KERNEL_WIDTH]> {
+        (0..=PHASES).map(|phase| {
+            let fraction = phase as f64 / PHASES as f64;
+            let mut taps = [0.0; KERNEL_WIDTH];
+            let mut sum = 0.0;
+
+            for (i, tap) in taps.iter_mut().enumerate() {
+                let t = i as f64 - (KERNEL_WIDTH / 2 - 1) as f64 - fraction;
+                let sinc = if t == 0.0 { 1.0 } else { (PI * 2.0 * CUTOFF * t).sin() / (PI * 2.0 * CUTOFF * t) };
+                let w = (i as f64 + 1.0 - fraction) / KERNEL_WIDTH as f64;
+                let window = 0.42 - 0.5 * (2.0 * PI * w).cos() + 0.08 * (4.0 * PI * w).cos();
+                let value = sinc * window.max(0.0);
+                *tap = value as f32;
+                sum += value;
+            }
+
+            for tap in taps.iter_mut() {
+                *tap /= sum as f32;
+            }
+            taps
+        }).collect()
+    }
+
+    /* Changes the resampling ratio; takes effect from the next delta */
+    pub fn set_rates(&mut self, clock_rate: f64, sample_rate: f64) {
+        self.factor = sample_rate / clock_rate;
+    }
+
+    /* Adds a change of `delta` in the input level at `time` clocks into the frame. A delta
+     * past the capacity is moved back to the last sample rather than dropped, since losing
+     * it would leave the integrated level off for good. */
+    pub fn add_delta(&mut self, time: u32, delta: f32) {
+        let position = self.offset + time as f64 * self.factor;
+        let last = self.buffer.len() - KERNEL_WIDTH;
+        let (index, phase) = if position as usize > last {
+            (last, 0)
+        }
+        else {
+            (position as usize, ((position - position.floor()) * PHASES as f64) as usize)
+        };
+
+        for (sample, tap) in self.buffer[index..(index + KERNEL_WIDTH)].iter_mut().zip(self.kernel[phase].iter()) {
+            *sample += delta * tap;
+        }
+    }
+
+    /* Ends a frame of `clocks` input clocks, making its output samples readable */
+    pub fn end_frame(&mut self, clocks: u32) {
+        self.offset += clocks as f64 * self.factor;
+    }
+
+    pub fn samples_available(&self) -> usize {
+        (self.offset as usize).min(self.buffer.len() - KERNEL_WIDTH)
+    }
+
+    /* Moves every finished sample to `out` */
+    pub fn read_samples(&mut self, out: &mut Vec<f32>) {
+        let count = self.samples_available();
+
+        for delta in &self.buffer[..count] {
+            self.integrator += delta;
+            out.push(self.integrator);
+        }
+
+        self.buffer.copy_within(count.., 0);
+        let len = self.buffer.len();
+        self.buffer[(len - count)..].fill(0.0);
+        self.offset -= count as f64;
+    }
+}
@@ -0,0 +1,210 @@
+use super::{
+    pulse::Pulse,
+    triangle::Triangle,
+    noise::Noise,
+    dmc::Dmc,
+};
+use crate::region::Region;
+
+pub const DMC_STALL_CYCLES: u32 = 4;            /* Worst case; the real stall depends on the CPU's current cycle */
+
+/* Frame counter steps, in CPU cycles from its reset. Dendy clones keep NTSC's. */
+fn frame_steps(region: Region) -> [u32; 5] {
+    match region {
+        Region::Pal => [8313, 16627, 24939, 33253, 41565],
+        Region::Ntsc | Region::Dendy => [7457, 14913, 22371, 29829, 37281],
+    }
+}
+
+/* A quarter second of output, the most kept when nobody drains `samples` */
+fn max_buffered_samples(region: Region) -> usize {
+    region.cpu_frequency() as usize / 4
+}
+
+/* The 2A03's sound generator, registers $4000-$4013, $4015 and $4017 */
+pub struct Apu {
+    pub pulse1: Pulse,
+    pub pulse2: Pulse,
+    pub triangle: Triangle,
+    pub noise: Noise,
+    pub dmc: Dmc,
+    pub samples: Vec<f32>,     /* Mixer output at the CPU clock rate, drained by the audio frontend */
+    region: Region,
+    steps: [u32; 5],
+    max_samples: usize,
+    cycle: u32,                 /* Frame counter position */
+    odd_cycle: bool,
+    five_step: bool,
+    irq_inhibit: bool,
+    frame_irq: bool,
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Apu {
+    pub fn new() -> Self {
+        Apu {
+            pulse1: Pulse::new(true),
+            pulse2: Pulse::new(false),
+            triangle: Triangle::default(),
+            noise: Noise::default(),
+            dmc: Dmc::default(),
+            samples: Vec::new(),
+            region: Region::default(),
+            steps: frame_steps(Region::default()),
+            max_samples: max_buffered_samples(Region::default()),
+            cycle: 0,
+            odd_cycle: false,
+            five_step: false,
+            irq_inhibit: false,
+            frame_irq: false,
+        }
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.steps = frame_steps(region);
+        self.max_samples = max_buffered_samples(region);
+        self.noise.set_region(region);
+        self.dmc.set_region(region);
+    }
+
+    /* Level of the APU's /IRQ output, true when asserted */
+    pub fn irq(&self) -> bool {
+        self.frame_irq || self.dmc.irq
+    }
+
+    /* Runs a single CPU cycle */
+    pub fn step(&mut self) {
+        self.clock_frame_counter();
+
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+        if self.odd_cycle {
+            self.pulse1.clock_timer();
+            self.pulse2.clock_timer();
+        }
+        self.odd_cycle = !self.odd_cycle;
+
+        if self.samples.len() < self.max_samples {
+            self.samples.push(self.output());
+        }
+    }
+
+    fn clock_frame_counter(&mut self) {
+        self.cycle += 1;
+
+        match self.steps.iter().position(|&step| step == self.cycle) {
+            Some(0) | Some(2) => self.quarter_frame(),
+            Some(1) => {
+                self.quarter_frame();
+                self.half_frame();
+            },
+            Some(3) if !self.five_step => {
+                self.quarter_frame();
+                self.half_frame();
+                if !self.irq_inhibit {
+                    self.frame_irq = true;
+                }
+                self.cycle = 0;
+            },
+            Some(4) if self.five_step => {
+                self.quarter_frame();
+                self.half_frame();
+                self.cycle = 0;
+            },
+            _ => {},
+        }
+    }
+
+    /* Envelopes and the triangle's linear counter */
+    fn quarter_frame(&mut self) {
+        self.pulse1.envelope.clock();
+        self.pulse2.envelope.clock();
+        self.noise.envelope.clock();
+        self.triangle.clock_linear();
+    }
+
+    /* Length counters and sweep units */
+    fn half_frame(&mut self) {
+        self.pulse1.length.clock();
+        self.pulse2.length.clock();
+        self.triangle.length.clock();
+        self.noise.length.clock();
+        self.pulse1.clock_sweep();
+        self.pulse2.clock_sweep();
+    }
+
+    /* The non-linear DAC mix, roughly 0.0 to 1.0 */
+    pub fn output(&self) -> f32 {
+        let pulse = (self.pulse1.output() + self.pulse2.output()) as f32;
+        let pulse_out = if pulse == 0.0 { 0.0 } else { 95.88 / (8128.0 / pulse + 100.0) };
+
+        let tnd = self.triangle.output() as f32 / 8227.0
+            + self.noise.output() as f32 / 12241.0
+            + self.dmc.output() as f32 / 22638.0;
+        let tnd_out = if tnd == 0.0 { 0.0 } else { 159.79 / (1.0 / tnd + 100.0) };
+
+        pulse_out + tnd_out
+    }
+
+    /* Registers{{{1*/
+    pub fn write_register(&mut self, reg: u16, byte: u8) {
+        match reg {
+            0x00..=0x03 => self.pulse1.write(reg, byte),
+            0x04..=0x07 => self.pulse2.write(reg, byte),
+            0x08..=0x0b => self.triangle.write(reg, byte),
+            0x0c..=0x0f => self.noise.write(reg, byte),
+            0x10..=0x13 => self.dmc.write(reg, byte),
+            0x15 => {
+                self.pulse1.length.set_enabled(byte & 0x01 != 0);
+                self.pulse2.length.set_enabled(byte & 0x02 != 0);
+                self.triangle.length.set_enabled(byte & 0x04 != 0);
+                self.noise.length.set_enabled(byte & 0x08 != 0);
+                self.dmc.set_enabled(byte & 0x10 != 0);
+            },
+            0x17 => {
+                self.five_step = byte & 0x80 != 0;
+                self.irq_inhibit = byte & 0x40 != 0;
+                if self.irq_inhibit {
+                    self.frame_irq = false;
+                }
+
+                /* The sequencer restarts, and 5-step mode clocks every unit straight away */
+                self.cycle = 0;
+                if self.five_step {
+                    self.quarter_frame();
+                    self.half_frame();
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /* $4015: reading acknowledges the frame interrupt */
+    pub fn read_status(&mut self) -> u8 {
+        let status = self.peek_status();
+        self.frame_irq = false;
+        status
+    }
+
+    pub fn peek_status(&self) -> u8 {
+        (self.pulse1.length.active() as u8)
+            | (self.pulse2.length.active() as u8) << 1
+            | (self.triangle.length.active() as u8) << 2
+            | (self.noise.length.active() as u8) << 3
+            | (self.dmc.active() as u8) << 4
+            | (self.frame_irq as u8) << 6
+            | (self.dmc.irq as u8) << 7
+    }
+    //}}}1
+}
@@ -0,0 +1,157 @@
+use crate::region::Region;
+
+/* Timer periods in CPU cycles. Dendy clones use the NTSC ones. */
+static NTSC_RATES: [u16; 16] = [428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54];
+static PAL_RATES: [u16; 16] = [398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50];
+
+fn rate_table(region: Region) -> &'static [u16; 16] {
+    match region {
+        Region::Pal => &PAL_RATES,
+        Region::Ntsc | Region::Dendy => &NTSC_RATES,
+    }
+}
+
+/* $4010-$4013: delta-modulated 1-bit samples read from CPU memory. The APU cannot reach
+ * the bus itself, so it raises `fetch_address` and the bus answers through `fill`. */
+pub struct Dmc {
+    pub irq: bool,
+    irq_enabled: bool,
+    looping: bool,
+    region: Region,
+    rate: u8,                   /* Index into the region's rate table */
+    timer: u16,
+    level: u8,
+    sample_address: u16,
+    sample_length: u16,
+    current_address: u16,
+    bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+    shift: u8,
+    bits_remaining: u8,
+    silence: bool,
+}
+
+impl Default for Dmc {
+    fn default() -> Self {
+        Dmc {
+            irq: false,
+            irq_enabled: false,
+            looping: false,
+            region: Region::default(),
+            rate: 0,
+            timer: 0,
+            level: 0,
+            sample_address: 0xc000,
+            sample_length: 1,
+            current_address: 0xc000,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift: 0,
+            bits_remaining: 8,
+            silence: true,
+        }
+    }
+}
+
+impl Dmc {
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    pub fn write(&mut self, reg: u16, byte: u8) {
+        match reg & 0x03 {
+            0 => {
+                self.irq_enabled = byte & 0x80 != 0;
+                if !self.irq_enabled {
+                    self.irq = false;
+                }
+                self.looping = byte & 0x40 != 0;
+                self.rate = byte & 0x0f;
+            },
+            1 => self.level = byte & 0x7f,
+            2 => self.sample_address = 0xc000 | ((byte as u16) << 6),
+            _ => self.sample_length = ((byte as u16) << 4) + 1,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.irq = false;
+        if !enabled {
+            self.bytes_remaining = 0;
+        }
+        else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    /* Address the memory reader wants to load, when the sample buffer has run dry */
+    pub fn fetch_address(&self) -> Option<u16> {
+        if self.sample_buffer.is_none() && self.bytes_remaining > 0 {
+            Some(self.current_address)
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn fill(&mut self, byte: u8) {
+        self.sample_buffer = Some(byte);
+        self.current_address = if self.current_address == 0xffff { 0x8000 } else { self.current_address + 1 };
+        self.bytes_remaining -= 1;
+
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            }
+            else if self.irq_enabled {
+                self.irq = true;
+            }
+        }
+    }
+
+    /* Clocked every CPU cycle */
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = rate_table(self.region)[self.rate as usize] - 1;
+
+        if !self.silence {
+            if self.shift & 1 == 1 {
+                if self.level <= 125 {
+                    self.level += 2;
+                }
+            }
+            else if self.level >= 2 {
+                self.level -= 2;
+            }
+        }
+        self.shift >>= 1;
+
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(byte) => {
+                    self.silence = false;
+                    self.shift = byte;
+                },
+                None => self.silence = true,
+            }
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        self.level
+    }
+}
@@ -0,0 +1,26 @@
+mod core;
+mod units;
+mod pulse;
+mod triangle;
+mod noise;
+mod dmc;
+mod blip;
+
+pub use self::core::{
+    Apu,
+    DMC_STALL_CYCLES,
+};
+
+pub use units::{
+    Envelope,
+    LengthCounter,
+    LENGTH_TABLE,
+};
+pub use pulse::Pulse;
+pub use triangle::Triangle;
+pub use noise::Noise;
+pub use dmc::Dmc;
+pub use blip::BlipBuffer;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,79 @@
+use super::units::{Envelope, LengthCounter};
+use crate::region::Region;
+
+/* Timer periods in CPU cycles. Dendy clones use the NTSC ones. */
+static NTSC_PERIODS: [u16; 16] = [4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068];
+static PAL_PERIODS: [u16; 16] = [4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778];
+
+fn period_table(region: Region) -> &'static [u16; 16] {
+    match region {
+        Region::Pal => &PAL_PERIODS,
+        Region::Ntsc | Region::Dendy => &NTSC_PERIODS,
+    }
+}
+
+/* $400C-$400F: a 15-bit LFSR, tapping bit 1 for the long sequence or bit 6 for the short one */
+pub struct Noise {
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+    short_mode: bool,
+    region: Region,
+    period: u8,                 /* Index into the region's period table */
+    timer: u16,
+    pub(super) shift: u16,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise {
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+            short_mode: false,
+            region: Region::default(),
+            period: 0,
+            timer: 0,
+            shift: 1,
+        }
+    }
+}
+
+impl Noise {
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    pub fn write(&mut self, reg: u16, byte: u8) {
+        match reg & 0x03 {
+            0 => {
+                self.length.halt = byte & 0x20 != 0;
+                self.envelope.write(byte);
+            },
+            1 => {},
+            2 => {
+                self.short_mode = byte & 0x80 != 0;
+                self.period = byte & 0x0f;
+            },
+            _ => {
+                self.length.load(byte >> 3);
+                self.envelope.start = true;
+            },
+        }
+    }
+
+    /* Clocked every CPU cycle */
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = period_table(self.region)[self.period as usize] - 1;
+            let tap = if self.short_mode { 6 } else { 1 };
+            let feedback = (self.shift ^ (self.shift >> tap)) & 1;
+            self.shift = (self.shift >> 1) | (feedback << 14);
+        }
+        else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.shift & 1 == 1 || !self.length.active() { 0 } else { self.envelope.output() }
+    }
+}
@@ -0,0 +1,106 @@
+use super::units::{Envelope, LengthCounter};
+
+static DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],   /* 12.5% */
+    [0, 1, 1, 0, 0, 0, 0, 0],   /* 25% */
+    [0, 1, 1, 1, 1, 0, 0, 0],   /* 50% */
+    [1, 0, 0, 1, 1, 1, 1, 1],   /* 25% negated */
+];
+
+/* $4000-$4003 and $4004-$4007. The two channels differ only in how the sweep unit
+ * negates: pulse 1 adds the ones' complement, pulse 2 the two's complement. */
+#[derive(Default)]
+pub struct Pulse {
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+    ones_complement: bool,
+    duty: u8,
+    step: u8,
+    period: u16,
+    timer: u16,
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_reload: bool,
+    sweep_divider: u8,
+}
+
+impl Pulse {
+    pub fn new(ones_complement: bool) -> Self {
+        Pulse { ones_complement, ..Default::default() }
+    }
+
+    pub fn write(&mut self, reg: u16, byte: u8) {
+        match reg & 0x03 {
+            0 => {
+                self.duty = byte >> 6;
+                self.length.halt = byte & 0x20 != 0;
+                self.envelope.write(byte);
+            },
+            1 => {
+                self.sweep_enabled = byte & 0x80 != 0;
+                self.sweep_period = (byte >> 4) & 0x07;
+                self.sweep_negate = byte & 0x08 != 0;
+                self.sweep_shift = byte & 0x07;
+                self.sweep_reload = true;
+            },
+            2 => self.period = (self.period & 0x0700) | byte as u16,
+            _ => {
+                self.period = (self.period & 0x00ff) | ((byte as u16 & 0x07) << 8);
+                self.length.load(byte >> 3);
+                self.step = 0;
+                self.envelope.start = true;
+            },
+        }
+    }
+
+    /* Clocked every APU cycle, i.e. every other CPU cycle */
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.step = (self.step + 1) & 0x07;
+        }
+        else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_sweep(&mut self) {
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.muted() {
+            self.period = self.sweep_target();
+        }
+
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        }
+        else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    fn sweep_target(&self) -> u16 {
+        let change = self.period >> self.sweep_shift;
+        if self.sweep_negate {
+            self.period.saturating_sub(change + self.ones_complement as u16)
+        }
+        else {
+            self.period + change
+        }
+    }
+
+    /* The sweep unit mutes the channel even while disabled */
+    fn muted(&self) -> bool {
+        self.period < 8 || self.sweep_target() > 0x07ff
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.muted() || !self.length.active() || DUTY_TABLE[self.duty as usize][self.step as usize] == 0 {
+            0
+        }
+        else {
+            self.envelope.output()
+        }
+    }
+}
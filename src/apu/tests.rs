@@ -0,0 +1,382 @@
+use super::{Apu, BlipBuffer, Noise, Pulse, Triangle, LENGTH_TABLE};
+use crate::region::Region;
+
+/* Runs the APU until `n` more CPU cycles have passed */
+fn run(apu: &mut Apu, n: u32) {
+    for _ in 0..n {
+        apu.step();
+    }
+}
+
+/* Length counter{{{1*/
+#[test]
+fn length_table_is_indexed_by_the_top_five_bits() {
+    /* Odd indices count up in steps of two, after the 254 of index 1 */
+    assert_eq!(LENGTH_TABLE[1], 254);
+    for index in (3..32).step_by(2) {
+        assert_eq!(LENGTH_TABLE[index], index as u8 - 1);
+    }
+
+    /* Even indices hold note lengths at two tempos */
+    let even: Vec<u8> = LENGTH_TABLE.iter().step_by(2).copied().collect();
+    assert_eq!(even, [10, 20, 40, 80, 160, 60, 14, 26, 12, 24, 48, 96, 192, 72, 16, 32]);
+
+    let mut apu = Apu::new();
+    apu.write_register(0x15, 0x01);
+    apu.write_register(0x03, 0x08);     /* Index 1 */
+    assert_eq!(apu.pulse1.length.value, 254);
+    apu.write_register(0x03, 0xf8);     /* Index 31 */
+    assert_eq!(apu.pulse1.length.value, 30);
+}
+
+#[test]
+fn length_counter_loads_only_while_enabled() {
+    let mut apu = Apu::new();
+    apu.write_register(0x0f, 0x00);
+    assert_eq!(apu.noise.length.value, 0);
+
+    apu.write_register(0x15, 0x08);
+    apu.write_register(0x0f, 0x00);
+    assert_eq!(apu.noise.length.value, 10);
+    assert_eq!(apu.read_status() & 0x08, 0x08);
+
+    apu.write_register(0x15, 0x00);
+    assert_eq!(apu.noise.length.value, 0);
+}
+
+#[test]
+fn length_counter_counts_half_frames_unless_halted() {
+    let mut apu = Apu::new();
+    apu.write_register(0x15, 0x03);
+    apu.write_register(0x03, 0x18);     /* Pulse 1, index 3: 2 half frames */
+    apu.write_register(0x04, 0x20);     /* Pulse 2 halted */
+    apu.write_register(0x07, 0x18);
+
+    run(&mut apu, 14913);
+    assert_eq!(apu.pulse1.length.value, 1);
+    run(&mut apu, 29829 - 14913);
+    assert_eq!(apu.pulse1.length.value, 0);
+    assert_eq!(apu.pulse2.length.value, 2);
+    assert_eq!(apu.read_status() & 0x03, 0x02);
+}
+//}}}1
+
+/* Frame counter{{{1*/
+#[test]
+fn four_step_mode_raises_irq_at_the_end_of_the_sequence() {
+    let mut apu = Apu::new();
+    run(&mut apu, 29828);
+    assert!(!apu.irq());
+
+    run(&mut apu, 1);
+    assert!(apu.irq());
+    assert_eq!(apu.peek_status() & 0x40, 0x40);
+
+    /* Reading $4015 acknowledges it */
+    assert_eq!(apu.read_status() & 0x40, 0x40);
+    assert!(!apu.irq());
+
+    /* The sequence repeats */
+    run(&mut apu, 29829);
+    assert!(apu.irq());
+}
+
+#[test]
+fn irq_inhibit_clears_and_blocks_the_frame_irq() {
+    let mut apu = Apu::new();
+    run(&mut apu, 29829);
+    assert!(apu.irq());
+
+    apu.write_register(0x17, 0x40);
+    assert!(!apu.irq());
+    run(&mut apu, 2 * 29829);
+    assert!(!apu.irq());
+}
+
+#[test]
+fn five_step_mode_never_raises_irq() {
+    let mut apu = Apu::new();
+    apu.write_register(0x17, 0x80);
+    run(&mut apu, 3 * 37281);
+    assert!(!apu.irq());
+}
+
+#[test]
+fn pal_frame_sequence_is_longer() {
+    let mut apu = Apu::new();
+    apu.set_region(Region::Pal);
+    run(&mut apu, 33252);
+    assert!(!apu.irq());
+    run(&mut apu, 1);
+    assert!(apu.irq());
+}
+//}}}1
+
+/* Noise{{{1*/
+/* With the shortest period the timer clocks the shift register every 4 CPU cycles */
+fn clock_shift_register(noise: &mut Noise) {
+    for _ in 0..4 {
+        noise.clock_timer();
+    }
+}
+
+#[test]
+fn noise_shift_register_feeds_back_bit_0_xor_bit_1() {
+    let mut noise = Noise::default();
+    let mut sequence = Vec::new();
+    for _ in 0..16 {
+        clock_shift_register(&mut noise);
+        sequence.push(noise.shift);
+    }
+
+    /* The seed of 1 shifts out, feeding a 1 into bit 14 that then walks down to bit 1 */
+    let mut expected: Vec<u16> = (0..14).map(|n| 0x4000 >> n).collect();
+    expected.extend([0x4001, 0x6000]);
+    assert_eq!(sequence, expected);
+}
+
+#[test]
+fn noise_sequence_lengths() {
+    for (mode, length) in [(0x00, 32767), (0x80, 93)] {
+        let mut noise = Noise::default();
+        noise.write(0x0e, mode);
+
+        let mut n = 0;
+        loop {
+            clock_shift_register(&mut noise);
+            n += 1;
+            if noise.shift == 1 {
+                break;
+            }
+            assert!(n <= 32767, "the shift register never returned to its seed");
+        }
+        assert_eq!(n, length);
+    }
+}
+
+#[test]
+fn noise_period_follows_the_region() {
+    for (region, period) in [(Region::Ntsc, 4068), (Region::Pal, 3778), (Region::Dendy, 4068)] {
+        let mut apu = Apu::new();
+        apu.set_region(region);
+        apu.write_register(0x0e, 0x0f);
+
+        apu.noise.clock_timer();        /* Reloads the timer with the new period */
+        let seed = apu.noise.shift;
+        let mut cycles = 0;
+        while apu.noise.shift == seed {
+            apu.noise.clock_timer();
+            cycles += 1;
+        }
+        assert_eq!(cycles, period, "{}", region);
+    }
+}
+//}}}1
+
+/* Pulse{{{1*/
+/* A pulse loud enough to see, on the 25% negated duty cycle whose first step is high */
+fn loud_pulse(ones_complement: bool, period: u16) -> Pulse {
+    let mut pulse = Pulse::new(ones_complement);
+    pulse.length.set_enabled(true);
+    pulse.write(0, 0xff);
+    pulse.write(2, period as u8);
+    pulse.write(3, (period >> 8) as u8);
+    pulse
+}
+
+/* Counts timer clocks from the fall after step 0 to the rise after step 2, which is two periods */
+fn measure_period(pulse: &mut Pulse) -> u32 {
+    while pulse.output() != 0 {
+        pulse.clock_timer();
+    }
+    let mut clocks = 0;
+    while pulse.output() == 0 {
+        pulse.clock_timer();
+        clocks += 1;
+    }
+    clocks / 2 - 1
+}
+
+#[test]
+fn sweep_mutes_short_periods_and_overflowing_targets() {
+    assert_eq!(loud_pulse(false, 0x100).output(), 15);
+    assert_eq!(loud_pulse(false, 0x007).output(), 0, "periods under 8 are muted");
+
+    /* A target past $7FF mutes even with the sweep disabled */
+    let mut pulse = loud_pulse(false, 0x7ff);
+    pulse.write(1, 0x01);
+    assert_eq!(pulse.output(), 0);
+    pulse.write(1, 0x09);                   /* Negated, the target is in range again */
+    assert_eq!(pulse.output(), 15);
+
+    /* A muted channel keeps its period through sweep clocks */
+    let mut pulse = loud_pulse(false, 0x600);
+    pulse.write(1, 0x81);
+    pulse.clock_sweep();
+    pulse.write(1, 0x08);                   /* Disabled, with a target of 0 to unmute it */
+    assert_eq!(measure_period(&mut pulse), 0x600);
+}
+
+#[test]
+fn sweep_negates_with_ones_complement_on_pulse_1() {
+    /* $100 - ($100 >> 1), less one more on pulse 1 */
+    for (ones_complement, period) in [(true, 0x7f), (false, 0x80)] {
+        let mut pulse = loud_pulse(ones_complement, 0x100);
+        assert_eq!(measure_period(&mut pulse), 0x100);
+
+        pulse.write(1, 0x89);               /* Enabled, divider period 0, negate, shift 1 */
+        pulse.clock_sweep();
+        assert_eq!(measure_period(&mut pulse), period);
+    }
+
+    let mut pulse = loud_pulse(false, 0x100);
+    pulse.write(1, 0x81);
+    pulse.clock_sweep();
+    assert_eq!(measure_period(&mut pulse), 0x180);
+}
+//}}}1
+
+/* Triangle{{{1*/
+/* A triangle with period 0, which steps on every timer clock while it is allowed to */
+fn triangle(control: bool, linear: u8) -> Triangle {
+    let mut triangle = Triangle::default();
+    triangle.length.set_enabled(true);
+    triangle.write(0, (control as u8) << 7 | linear);
+    triangle.write(3, 0x08);
+    triangle
+}
+
+/* Whether four timer clocks move the sequencer. The sequence repeats a level at most
+ * twice in a row, so four steps always change it. */
+fn moves(triangle: &mut Triangle) -> bool {
+    let level = triangle.output();
+    for _ in 0..4 {
+        triangle.clock_timer();
+    }
+    triangle.output() != level
+}
+
+#[test]
+fn linear_counter_reloads_then_counts_down_to_silence() {
+    let mut triangle = triangle(false, 2);
+    assert!(!moves(&mut triangle), "the counter is zero until its first clock");
+
+    triangle.clock_linear();                /* Reloads 2 and, with control clear, drops the reload flag */
+    assert!(moves(&mut triangle));
+    triangle.clock_linear();
+    assert!(moves(&mut triangle));
+    triangle.clock_linear();
+    assert!(!moves(&mut triangle));
+
+    /* Silenced, the output holds its level rather than falling to zero */
+    assert_eq!(triangle.output(), 7);
+
+    /* A write to $400B sets the reload flag again */
+    triangle.write(3, 0x08);
+    triangle.clock_linear();
+    assert!(moves(&mut triangle));
+}
+
+#[test]
+fn control_flag_keeps_reloading_the_linear_counter() {
+    let mut triangle = triangle(true, 1);
+    for _ in 0..10 {
+        triangle.clock_linear();
+        assert!(moves(&mut triangle));
+    }
+}
+//}}}1
+
+/* DMC{{{1*/
+/* Steps the APU, answering each DMC fetch with `byte` like the bus does. Returns the
+ * cycle, address and output level at every fetch. */
+fn run_dmc(apu: &mut Apu, cycles: u32, byte: u8) -> Vec<(u32, u16, u8)> {
+    let mut fetches = Vec::new();
+    for cycle in 0..cycles {
+        apu.step();
+        if let Some(addr) = apu.dmc.fetch_address() {
+            fetches.push((cycle, addr, apu.dmc.output()));
+            apu.dmc.fill(byte);
+        }
+    }
+    fetches
+}
+
+#[test]
+fn dmc_fetches_its_sample_and_plays_it() {
+    let mut apu = Apu::new();
+    apu.write_register(0x10, 0x0f);         /* 54 cycles per bit */
+    apu.write_register(0x11, 0x20);
+    apu.write_register(0x12, 0xff);         /* $FFC0 */
+    apu.write_register(0x13, 0x04);         /* 65 bytes */
+    apu.write_register(0x15, 0x10);
+    assert_eq!(apu.read_status() & 0x10, 0x10);
+
+    let fetches = run_dmc(&mut apu, 70 * 8 * 54, 0xff);
+    assert_eq!(fetches.len(), 65);
+    assert_eq!(fetches[0].1, 0xffc0);
+    assert_eq!(fetches[63].1, 0xffff);
+    assert_eq!(fetches[64].1, 0x8000, "the address wraps to $8000");
+
+    /* A byte lasts eight bits, and each set bit raises the level by two */
+    assert_eq!(fetches[2].0 - fetches[1].0, 8 * 54);
+    assert_eq!(fetches[1].2, 0x20);
+    assert_eq!(fetches[2].2, 0x30);
+    assert_eq!(apu.dmc.output(), 126, "the level stops short of the top");
+
+    assert_eq!(apu.read_status() & 0x10, 0);
+    assert!(!apu.dmc.irq);
+}
+
+#[test]
+fn dmc_raises_irq_at_the_end_of_a_sample() {
+    let mut apu = Apu::new();
+    apu.write_register(0x10, 0x8f);
+    apu.write_register(0x13, 0x00);         /* 1 byte */
+    apu.write_register(0x15, 0x10);
+
+    assert_eq!(run_dmc(&mut apu, 1, 0x00).len(), 1);
+    assert!(apu.irq());
+    assert_eq!(apu.read_status() & 0x80, 0x80);
+    assert!(apu.irq(), "reading $4015 only acknowledges the frame interrupt");
+
+    apu.write_register(0x15, 0x10);
+    assert!(!apu.irq(), "writing $4015 acknowledges it");
+
+    run_dmc(&mut apu, 8 * 54, 0x00);
+    assert!(apu.irq());
+    apu.write_register(0x10, 0x0f);
+    assert!(!apu.irq(), "so does clearing the enable bit");
+}
+
+#[test]
+fn looping_dmc_restarts_without_irq() {
+    let mut apu = Apu::new();
+    apu.write_register(0x10, 0xcf);
+    apu.write_register(0x12, 0x10);         /* $C400 */
+    apu.write_register(0x13, 0x00);
+    apu.write_register(0x15, 0x10);
+
+    let fetches = run_dmc(&mut apu, 10 * 8 * 54, 0x00);
+    assert!(fetches.len() >= 10);
+    assert!(fetches.iter().all(|&(_, addr, _)| addr == 0xc400));
+    assert!(apu.dmc.active());
+    assert!(!apu.dmc.irq);
+}
+//}}}1
+
+/* Band-limited synthesis{{{1*/
+#[test]
+fn deltas_past_the_buffer_still_reach_the_output() {
+    let mut blip = BlipBuffer::new(1.0, 1.0, 8);
+    blip.add_delta(2, 1.0);
+    blip.add_delta(100, 1.0);               /* Well past the capacity of 8 samples */
+
+    let mut samples = Vec::new();
+    for _ in 0..3 {
+        blip.end_frame(8);
+        blip.read_samples(&mut samples);
+    }
+    assert!((samples.last().unwrap() - 2.0).abs() < 1e-4, "the level ends at {}", samples.last().unwrap());
+}
+//}}}1
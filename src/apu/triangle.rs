@@ -0,0 +1,69 @@
+use super::units::LengthCounter;
+
+static SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10,  9,  8,  7,  6,  5,  4,  3,  2,  1,  0,
+     0,  1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15,
+];
+
+/* $4008-$400B */
+#[derive(Default)]
+pub struct Triangle {
+    pub length: LengthCounter,
+    control: bool,          /* Also the length counter halt flag */
+    linear_reload_value: u8,
+    linear_counter: u8,
+    linear_reload: bool,
+    period: u16,
+    timer: u16,
+    step: u8,
+}
+
+impl Triangle {
+    pub fn write(&mut self, reg: u16, byte: u8) {
+        match reg & 0x03 {
+            0 => {
+                self.control = byte & 0x80 != 0;
+                self.length.halt = self.control;
+                self.linear_reload_value = byte & 0x7f;
+            },
+            1 => {},
+            2 => self.period = (self.period & 0x0700) | byte as u16,
+            _ => {
+                self.period = (self.period & 0x00ff) | ((byte as u16 & 0x07) << 8);
+                self.length.load(byte >> 3);
+                self.linear_reload = true;
+            },
+        }
+    }
+
+    /* Clocked every CPU cycle; the sequencer only moves while both counters are non-zero */
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            if self.length.active() && self.linear_counter > 0 {
+                self.step = (self.step + 1) & 0x1f;
+            }
+        }
+        else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_linear(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        }
+        else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    /* A halted triangle holds its last level rather than dropping to zero */
+    pub fn output(&self) -> u8 {
+        SEQUENCE[self.step as usize]
+    }
+}
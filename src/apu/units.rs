@@ -0,0 +1,81 @@
+/* Length counter load values, indexed by the top five bits of the channel's fourth register */
+pub static LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20,  2, 40,  4, 80,  6, 160,  8, 60, 10, 14, 12, 26, 14,
+    12,  16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+/* Volume envelope shared by the pulse and noise channels, clocked every quarter frame */
+#[derive(Default)]
+pub struct Envelope {
+    pub start: bool,
+    pub looping: bool,      /* Same bit as the length counter halt flag */
+    pub constant: bool,
+    pub volume: u8,         /* Constant volume, or the divider period */
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    pub fn write(&mut self, byte: u8) {
+        self.looping = byte & 0x20 != 0;
+        self.constant = byte & 0x10 != 0;
+        self.volume = byte & 0x0f;
+    }
+
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.volume;
+        }
+        else if self.divider == 0 {
+            self.divider = self.volume;
+            if self.decay > 0 {
+                self.decay -= 1;
+            }
+            else if self.looping {
+                self.decay = 15;
+            }
+        }
+        else {
+            self.divider -= 1;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.constant { self.volume } else { self.decay }
+    }
+}
+
+/* Silences a channel after a programmed time, clocked every half frame */
+#[derive(Default)]
+pub struct LengthCounter {
+    pub value: u8,
+    pub halt: bool,
+    pub enabled: bool,
+}
+
+impl LengthCounter {
+    pub fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.value = LENGTH_TABLE[index as usize & 0x1f];
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.value = 0;
+        }
+    }
+
+    pub fn clock(&mut self) {
+        if self.value > 0 && !self.halt {
+            self.value -= 1;
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.value > 0
+    }
+}
@@ -0,0 +1,48 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(io::Error),
+    MissingHeader(usize),           /* File is shorter than the 16-byte header */
+    BadMagic([u8; 4]),              /* First four bytes are not "NES\x1a" */
+    NoPrgRom,                       /* Header declares zero bytes of PRG ROM */
+    SizeOverflow(&'static str),     /* NES 2.0 exponent notation describes an impossible size */
+    Truncated {                     /* File ends before the declared section does */
+        section: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    UnsupportedMapper(u16),
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CartridgeError::Io(e) => write!(f, "Error reading rom: {}", e),
+            CartridgeError::MissingHeader(len) =>
+                write!(f, "File is {} bytes long, too short for an iNES header", len),
+            CartridgeError::BadMagic(magic) =>
+                write!(f, "Not an iNES file (magic bytes {:02x?})", magic),
+            CartridgeError::NoPrgRom => write!(f, "Header declares no PRG ROM"),
+            CartridgeError::SizeOverflow(section) => write!(f, "{} size does not fit in memory", section),
+            CartridgeError::Truncated { section, expected, found } =>
+                write!(f, "{} is truncated: expected {} bytes, found {}", section, expected, found),
+            CartridgeError::UnsupportedMapper(mapper) => write!(f, "Mapper {} is not supported", mapper),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CartridgeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CartridgeError {
+    fn from(e: io::Error) -> Self {
+        CartridgeError::Io(e)
+    }
+}
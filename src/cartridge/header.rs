@@ -0,0 +1,152 @@
+use super::CartridgeError;
+use crate::region::Region;
+
+pub const HEADER_LEN: usize = 16;
+pub const TRAINER_LEN: usize = 512;
+pub const MAGIC: [u8; 4] = *b"NES\x1a";
+
+pub const PRG_ROM_UNIT: usize = 16 * 1024;
+pub const CHR_ROM_UNIT: usize = 8 * 1024;
+const PRG_RAM_UNIT: usize = 8 * 1024;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    INes,               /* Original iNES header, bytes 8-15 mostly unreliable */
+    Nes2,               /* NES 2.0 header, identified by bits 2-3 of byte 7 being 0b10 */
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mirroring {
+    Horizontal,         /* $2000 = $2400, $2800 = $2C00 (vertical scrolling games) */
+    Vertical,           /* $2000 = $2800, $2400 = $2C00 (horizontal scrolling games) */
+    FourScreen,         /* Cartridge supplies the extra 2 KB of nametable RAM */
+    SingleScreenLower,  /* All four nametables map to the first 1 KB of VRAM */
+    SingleScreenUpper,  /* All four nametables map to the second 1 KB of VRAM */
+}
+
+#[derive(Clone, Debug)]
+pub struct Header {
+    pub format: Format,
+    pub prg_rom_size: usize,
+    pub chr_rom_size: usize,
+    pub mapper: u16,
+    pub submapper: u8,
+    pub mirroring: Mirroring,
+    pub battery: bool,
+    pub trainer: bool,
+    pub region: Region,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+}
+
+impl Header {
+    pub fn parse(bytes: &[u8]) -> Result<Header, CartridgeError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(CartridgeError::MissingHeader(bytes.len()));
+        }
+
+        let magic = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if magic != MAGIC {
+            return Err(CartridgeError::BadMagic(magic));
+        }
+
+        let flags6 = bytes[6];
+        let flags7 = bytes[7];
+
+        let mirroring = if flags6 & 0x08 != 0 {
+            Mirroring::FourScreen
+        }
+        else if flags6 & 0x01 != 0 {
+            Mirroring::Vertical
+        }
+        else {
+            Mirroring::Horizontal
+        };
+        let battery = flags6 & 0x02 != 0;
+        let trainer = flags6 & 0x04 != 0;
+
+        let header = if flags7 & 0x0c == 0x08 {
+            Header::parse_nes2(bytes, mirroring, battery, trainer)?
+        }
+        else {
+            Header::parse_ines(bytes, mirroring, battery, trainer)
+        };
+
+        if header.prg_rom_size == 0 {
+            return Err(CartridgeError::NoPrgRom);
+        }
+
+        Ok(header)
+    }
+
+    fn parse_ines(bytes: &[u8], mirroring: Mirroring, battery: bool, trainer: bool) -> Header {
+        /* Headers written by old tools ("DiskDude!") put garbage in bytes 7-15; the
+         * usual heuristic is to trust the upper mapper nibble only when 12-15 are clear. */
+        let mapper_high = if bytes[12..16].iter().all(|b| *b == 0) { bytes[7] & 0xf0 } else { 0 };
+        let chr_rom_size = bytes[5] as usize * CHR_ROM_UNIT;
+
+        Header {
+            format: Format::INes,
+            prg_rom_size: bytes[4] as usize * PRG_ROM_UNIT,
+            chr_rom_size,
+            mapper: (mapper_high | (bytes[6] >> 4)) as u16,
+            submapper: 0,
+            mirroring,
+            battery,
+            trainer,
+            region: if bytes[9] & 0x01 != 0 { Region::Pal } else { Region::Ntsc },
+            /* A value of zero means 8 KB for compatibility */
+            prg_ram_size: (bytes[8].max(1) as usize) * PRG_RAM_UNIT,
+            prg_nvram_size: 0,
+            chr_ram_size: if chr_rom_size == 0 { CHR_ROM_UNIT } else { 0 },
+            chr_nvram_size: 0,
+        }
+    }
+
+    fn parse_nes2(bytes: &[u8], mirroring: Mirroring, battery: bool, trainer: bool) -> Result<Header, CartridgeError> {
+        let mapper = ((bytes[8] as u16 & 0x0f) << 8) | (bytes[7] & 0xf0) as u16 | (bytes[6] >> 4) as u16;
+
+        Ok(Header {
+            format: Format::Nes2,
+            prg_rom_size: Header::nes2_rom_size(bytes[4], bytes[9] & 0x0f, PRG_ROM_UNIT, "PRG ROM")?,
+            chr_rom_size: Header::nes2_rom_size(bytes[5], bytes[9] >> 4, CHR_ROM_UNIT, "CHR ROM")?,
+            mapper,
+            submapper: bytes[8] >> 4,
+            mirroring,
+            battery,
+            trainer,
+            region: match bytes[12] & 0x03 {
+                0 | 2 => Region::Ntsc,  /* Images that run anywhere get the most common console */
+                1 => Region::Pal,
+                _ => Region::Dendy,
+            },
+            prg_ram_size: Header::nes2_ram_size(bytes[10] & 0x0f),
+            prg_nvram_size: Header::nes2_ram_size(bytes[10] >> 4),
+            chr_ram_size: Header::nes2_ram_size(bytes[11] & 0x0f),
+            chr_nvram_size: Header::nes2_ram_size(bytes[11] >> 4),
+        })
+    }
+
+    /* NES 2.0 ROM sizes are a 12-bit unit count, unless the upper nibble is $F in which
+     * case the low byte is EEEEEEMM and the size is 2^E * (MM * 2 + 1) bytes. */
+    fn nes2_rom_size(lsb: u8, msb: u8, unit: usize, section: &'static str) -> Result<usize, CartridgeError> {
+        if msb == 0x0f {
+            let exponent = (lsb >> 2) as u32;
+            let multiplier = ((lsb & 0x03) as usize) * 2 + 1;
+
+            1_usize.checked_shl(exponent)
+                .and_then(|size| size.checked_mul(multiplier))
+                .ok_or(CartridgeError::SizeOverflow(section))
+        }
+        else {
+            Ok((((msb as usize) << 8) | lsb as usize) * unit)
+        }
+    }
+
+    /* RAM sizes are stored as a shift count: 64 << n bytes, with zero meaning none */
+    fn nes2_ram_size(shift: u8) -> usize {
+        if shift == 0 { 0 } else { 64 << shift }
+    }
+}
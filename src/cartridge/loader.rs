@@ -0,0 +1,118 @@
+use std::{
+    fs,
+    path::Path,
+};
+
+use super::{
+    CartridgeError,
+    Header,
+    header::{HEADER_LEN, TRAINER_LEN, CHR_ROM_UNIT},
+};
+
+const TRAINER_OFFSET: usize = 0x1000; /* Trainers are loaded at $7000, $1000 into PRG RAM */
+
+pub struct Cartridge {
+    pub header: Header,
+    pub prg_rom: Vec<u8>,
+    pub prg_ram: Vec<u8>,
+    pub chr: Vec<u8>,       /* CHR ROM, or zeroed CHR RAM when the board has no CHR ROM */
+}
+
+impl Cartridge {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Cartridge, CartridgeError> {
+        let bytes = fs::read(path)?;
+        Cartridge::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Cartridge, CartridgeError> {
+        let header = Header::parse(bytes)?;
+        let mut offset = HEADER_LEN;
+
+        let trainer = if header.trainer {
+            let trainer = Cartridge::section(bytes, offset, TRAINER_LEN, "Trainer")?;
+            offset += TRAINER_LEN;
+            Some(trainer)
+        }
+        else {
+            None
+        };
+
+        let prg_rom = Cartridge::section(bytes, offset, header.prg_rom_size, "PRG ROM")?.to_vec();
+        offset += header.prg_rom_size;
+
+        let chr = if header.chr_rom_size > 0 {
+            Cartridge::section(bytes, offset, header.chr_rom_size, "CHR ROM")?.to_vec()
+        }
+        else {
+            /* Boards without CHR ROM that do not say otherwise have one bank of CHR RAM */
+            let size = header.chr_ram_size + header.chr_nvram_size;
+            vec![0; if size == 0 { CHR_ROM_UNIT } else { size }]
+        };
+
+        let mut prg_ram = vec![0; header.prg_ram_size + header.prg_nvram_size];
+        if let Some(trainer) = trainer {
+            if prg_ram.len() < TRAINER_OFFSET + TRAINER_LEN {
+                prg_ram.resize(TRAINER_OFFSET + TRAINER_LEN, 0);
+            }
+            prg_ram[TRAINER_OFFSET..(TRAINER_OFFSET + TRAINER_LEN)].copy_from_slice(trainer);
+        }
+
+        Ok(Cartridge { header, prg_rom, prg_ram, chr })
+    }
+
+    pub fn has_chr_ram(&self) -> bool {
+        self.header.chr_rom_size == 0
+    }
+
+    /* PRG RAM sits at $6000-$7FFF on every board that has it */
+    pub fn read_prg_ram(&self, addr: u16) -> Option<u8> {
+        if self.prg_ram.is_empty() {
+            return None;
+        }
+
+        Some(self.prg_ram[(addr as usize - 0x6000) % self.prg_ram.len()])
+    }
+
+    pub fn write_prg_ram(&mut self, addr: u16, byte: u8) {
+        if !self.prg_ram.is_empty() {
+            let len = self.prg_ram.len();
+            self.prg_ram[(addr as usize - 0x6000) % len] = byte;
+        }
+    }
+
+    /* Bank numbers wrap around the ROM size, the same way unconnected address lines do */
+    pub fn prg_bank(&self, bank: usize, size: usize) -> &[u8] {
+        let start = Cartridge::bank_start(self.prg_rom.len(), bank, size);
+        &self.prg_rom[start..(start + size).min(self.prg_rom.len())]
+    }
+
+    pub fn chr_bank(&self, bank: usize, size: usize) -> &[u8] {
+        let start = Cartridge::bank_start(self.chr.len(), bank, size);
+        &self.chr[start..(start + size).min(self.chr.len())]
+    }
+
+    pub fn chr_bank_mut(&mut self, bank: usize, size: usize) -> &mut [u8] {
+        let start = Cartridge::bank_start(self.chr.len(), bank, size);
+        let end = (start + size).min(self.chr.len());
+        &mut self.chr[start..end]
+    }
+
+    pub fn prg_bank_count(&self, size: usize) -> usize {
+        (self.prg_rom.len() / size).max(1)
+    }
+
+    fn bank_start(len: usize, bank: usize, size: usize) -> usize {
+        (bank % (len / size).max(1)) * size
+    }
+
+    fn section<'a>(bytes: &'a [u8], offset: usize, len: usize, section: &'static str) -> Result<&'a [u8], CartridgeError> {
+        match offset.checked_add(len) {
+            Some(end) if end <= bytes.len() => Ok(&bytes[offset..end]),
+            _ => Err(CartridgeError::Truncated {
+                section,
+                expected: len,
+                found: bytes.len().saturating_sub(offset),
+            }),
+        }
+    }
+}
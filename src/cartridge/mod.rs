@@ -0,0 +1,18 @@
+mod error;
+mod header;
+mod loader;
+
+pub use error::CartridgeError;
+pub use loader::Cartridge;
+
+pub use header::{
+    Header,
+    Format,
+    Mirroring,
+};
+
+#[cfg(test)]
+pub(crate) mod test_image;
+
+#[cfg(test)]
+mod tests;
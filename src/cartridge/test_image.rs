@@ -0,0 +1,76 @@
+/* iNES images for the tests, so no module has to lay out a header by hand */
+use super::{
+    Cartridge,
+    header::{HEADER_LEN, MAGIC, PRG_ROM_UNIT, CHR_ROM_UNIT},
+};
+
+/* A bare iNES header. The low nibble of the mapper number goes in flags 6, the high one in flags 7. */
+pub(crate) fn header(prg_banks: u8, chr_banks: u8, flags6: u8, flags7: u8) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend([prg_banks, chr_banks, flags6, flags7]);
+    bytes.resize(HEADER_LEN, 0);
+    bytes
+}
+
+/* A whole image whose ROM is zero until filled. No CHR banks gives the board CHR RAM. */
+pub(crate) struct TestImage {
+    header: Vec<u8>,
+    prg: Vec<u8>,
+    chr: Vec<u8>,
+}
+
+impl TestImage {
+    pub(crate) fn new(mapper: u8, prg_banks: u8, chr_banks: u8) -> Self {
+        TestImage {
+            header: header(prg_banks, chr_banks, mapper << 4, mapper & 0xf0),
+            prg: vec![0; prg_banks as usize * PRG_ROM_UNIT],
+            chr: vec![0; chr_banks as usize * CHR_ROM_UNIT],
+        }
+    }
+
+    /* Mirroring, battery and four-screen bits, next to the mapper nibble */
+    pub(crate) fn with_flags6(mut self, flags: u8) -> Self {
+        self.header[6] |= flags & 0x0f;
+        self
+    }
+
+    pub(crate) fn with_header_byte(mut self, index: usize, value: u8) -> Self {
+        self.header[index] = value;
+        self
+    }
+
+    /* Each PRG and CHR byte is `fill` of its offset */
+    pub(crate) fn with_prg_fill(mut self, fill: impl Fn(usize) -> u8) -> Self {
+        self.prg.iter_mut().enumerate().for_each(|(i, byte)| *byte = fill(i));
+        self
+    }
+
+    pub(crate) fn with_chr_fill(mut self, fill: impl Fn(usize) -> u8) -> Self {
+        self.chr.iter_mut().enumerate().for_each(|(i, byte)| *byte = fill(i));
+        self
+    }
+
+    /* `bytes` at `offset` into PRG ROM */
+    pub(crate) fn with_prg(mut self, offset: usize, bytes: &[u8]) -> Self {
+        self.prg[offset..(offset + bytes.len())].copy_from_slice(bytes);
+        self
+    }
+
+    /* The NMI, reset and IRQ vectors at the end of the last bank */
+    pub(crate) fn with_vectors(self, nmi: u16, reset: u16, irq: u16) -> Self {
+        let offset = self.prg.len() - 6;
+        let vectors: Vec<u8> = [nmi, reset, irq].iter().flat_map(|vector| vector.to_le_bytes()).collect();
+        self.with_prg(offset, &vectors)
+    }
+
+    pub(crate) fn bytes(self) -> Vec<u8> {
+        let mut bytes = self.header;
+        bytes.extend(self.prg);
+        bytes.extend(self.chr);
+        bytes
+    }
+
+    pub(crate) fn cartridge(self) -> Cartridge {
+        Cartridge::from_bytes(&self.bytes()).unwrap()
+    }
+}
@@ -0,0 +1,142 @@
+use super::{
+    Cartridge,
+    CartridgeError,
+    Format,
+    Mirroring,
+    header::{MAGIC, TRAINER_LEN, PRG_ROM_UNIT, CHR_ROM_UNIT},
+    test_image::header,
+};
+use crate::{
+    mapper,
+    region::Region,
+};
+
+/* Appends `len` bytes that start with `first` and count up from there */
+fn append(bytes: &mut Vec<u8>, len: usize, first: u8) {
+    bytes.extend((0..len).map(|i| first.wrapping_add(i as u8)));
+}
+
+#[test]
+fn short_file_has_no_header() {
+    assert!(matches!(Cartridge::from_bytes(&MAGIC), Err(CartridgeError::MissingHeader(4))));
+}
+
+#[test]
+fn bad_magic_is_rejected() {
+    let mut bytes = header(1, 1, 0, 0);
+    bytes[3] = 0x1b;
+    assert!(matches!(Cartridge::from_bytes(&bytes), Err(CartridgeError::BadMagic(magic)) if magic == *b"NES\x1b"));
+}
+
+#[test]
+fn header_without_prg_rom_is_rejected() {
+    assert!(matches!(Cartridge::from_bytes(&header(0, 1, 0, 0)), Err(CartridgeError::NoPrgRom)));
+}
+
+#[test]
+fn truncated_prg_rom_reports_its_size() {
+    let mut bytes = header(2, 0, 0, 0);
+    append(&mut bytes, PRG_ROM_UNIT + 100, 0);
+
+    match Cartridge::from_bytes(&bytes) {
+        Err(CartridgeError::Truncated { section, expected, found }) => {
+            assert_eq!(section, "PRG ROM");
+            assert_eq!(expected, 2 * PRG_ROM_UNIT);
+            assert_eq!(found, PRG_ROM_UNIT + 100);
+        },
+        _ => panic!("a short PRG ROM must be reported as truncated"),
+    }
+}
+
+#[test]
+fn ines_header_fields() {
+    let mut bytes = header(2, 1, 0x13, 0x10);  /* Mapper $11, battery, vertical mirroring */
+    bytes[9] = 0x01;
+    append(&mut bytes, 2 * PRG_ROM_UNIT, 0x40);
+    append(&mut bytes, CHR_ROM_UNIT, 0x80);
+
+    let cartridge = Cartridge::from_bytes(&bytes).unwrap();
+    let header = &cartridge.header;
+    assert_eq!(header.format, Format::INes);
+    assert_eq!(header.mapper, 0x11);
+    assert_eq!(header.mirroring, Mirroring::Vertical);
+    assert!(header.battery);
+    assert_eq!(header.region, Region::Pal);
+    assert_eq!(cartridge.prg_rom[0], 0x40);
+    assert_eq!(cartridge.chr[0], 0x80);
+    assert!(!cartridge.has_chr_ram());
+}
+
+#[test]
+fn ines_without_chr_rom_gets_a_bank_of_chr_ram() {
+    let mut bytes = header(1, 0, 0, 0);
+    append(&mut bytes, PRG_ROM_UNIT, 0);
+
+    let cartridge = Cartridge::from_bytes(&bytes).unwrap();
+    assert!(cartridge.has_chr_ram());
+    assert_eq!(cartridge.chr.len(), CHR_ROM_UNIT);
+}
+
+#[test]
+fn trainer_is_skipped_and_loaded_at_7000() {
+    let mut bytes = header(1, 1, 0x04, 0);
+    append(&mut bytes, TRAINER_LEN, 0x10);
+    append(&mut bytes, PRG_ROM_UNIT, 0x20);
+    append(&mut bytes, CHR_ROM_UNIT, 0x30);
+
+    let cartridge = Cartridge::from_bytes(&bytes).unwrap();
+    assert!(cartridge.header.trainer);
+    assert_eq!(cartridge.read_prg_ram(0x7000), Some(0x10));
+    assert_eq!(cartridge.read_prg_ram(0x71ff), Some(0x0f));
+    assert_eq!(cartridge.prg_rom[0], 0x20);
+    assert_eq!(cartridge.chr[0], 0x30);
+}
+
+#[test]
+fn nes2_header_fields() {
+    let mut bytes = header(1, 0, 0x08, 0x48);  /* Four-screen, NES 2.0, mapper nibble 4 */
+    bytes[8] = 0x31;            /* Submapper 3, mapper bits 8-11 = 1 */
+    bytes[10] = 0x70;           /* 8 KB of PRG NVRAM, no PRG RAM */
+    bytes[11] = 0x07;           /* 8 KB of CHR RAM */
+    bytes[12] = 0x03;           /* Dendy */
+    append(&mut bytes, PRG_ROM_UNIT, 0);
+
+    let cartridge = Cartridge::from_bytes(&bytes).unwrap();
+    let header = &cartridge.header;
+    assert_eq!(header.format, Format::Nes2);
+    assert_eq!(header.mapper, 0x140);
+    assert_eq!(header.submapper, 3);
+    assert_eq!(header.mirroring, Mirroring::FourScreen);
+    assert_eq!(header.region, Region::Dendy);
+    assert_eq!((header.prg_ram_size, header.prg_nvram_size), (0, 8 * 1024));
+    assert_eq!(header.chr_ram_size, 8 * 1024);
+    assert_eq!(cartridge.prg_ram.len(), 8 * 1024);
+}
+
+#[test]
+fn nes2_multiple_region_runs_as_ntsc() {
+    let mut bytes = header(1, 1, 0, 0x08);
+    bytes[12] = 0x02;
+    append(&mut bytes, PRG_ROM_UNIT + CHR_ROM_UNIT, 0);
+
+    assert_eq!(Cartridge::from_bytes(&bytes).unwrap().header.region, Region::Ntsc);
+}
+
+#[test]
+fn nes2_exponent_rom_size() {
+    let mut bytes = header(0x3a, 0, 0, 0x08);  /* 2^14 * (2 * 2 + 1) bytes */
+    bytes[9] = 0x0f;
+    append(&mut bytes, 5 * 16 * 1024, 0);
+
+    let cartridge = Cartridge::from_bytes(&bytes).unwrap();
+    assert_eq!(cartridge.header.prg_rom_size, 5 * 16 * 1024);
+}
+
+#[test]
+fn unsupported_mapper_is_reported() {
+    let mut bytes = header(1, 1, 0x50, 0);     /* Mapper 5, MMC5 */
+    append(&mut bytes, PRG_ROM_UNIT + CHR_ROM_UNIT, 0);
+
+    let cartridge = Cartridge::from_bytes(&bytes).unwrap();
+    assert!(matches!(mapper::from_cartridge(cartridge), Err(CartridgeError::UnsupportedMapper(5))));
+}
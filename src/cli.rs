@@ -0,0 +1,281 @@
+use std::fmt;
+
+use nes::{
+    region::Region,
+    headless::Condition,
+};
+
+pub const USAGE: &str = "\
+Usage: nes [OPTIONS] <ROM>
+       nes headless --frames <N> [OPTIONS] <ROM>
+
+Options:
+    --scale <N>         Window scale, 1 to 8 (default 2)
+    --no-debug          Hide the memory and stack panel
+    --font <FILE>       TTF font for the debug panel
+    --region <REGION>   ntsc, pal or dendy (default from the ROM header)
+    --fullscreen        Start in fullscreen
+    --mute              Do not open an audio device
+    --input <FILE>      Load controller bindings from FILE
+    --headless          Same as the headless command
+    --frames <N>        Stop after N frames
+    -h, --help          Print this message
+
+Headless options:
+    --frames <N>        Run at most N frames
+    --until <COND>      Stop early once COND holds, e.g. 6000!=80 (hex)
+    --script <FILE>     Feed controller input from FILE
+    --region <REGION>   ntsc, pal or dendy (default from the ROM header)
+    --frame-out <FILE>  Write the last frame as a PPM image
+    --audio-out <FILE>  Write the audio as a WAV file
+    --ram-out <FILE>    Write the 2 KB of internal RAM";
+
+const MAX_SCALE: u32 = 8;
+
+/* Options for the window, audio device and controllers, which a headless run has none of */
+const GUI_ONLY: [&str; 6] = ["--scale", "--no-debug", "--font", "--fullscreen", "--mute", "--input"];
+
+#[derive(Debug)]
+pub struct Options {
+    pub rom: String,
+    pub scale: u32,
+    pub debug: bool,
+    pub font: Option<String>,
+    pub region: Option<Region>,     /* Overrides the ROM header */
+    pub fullscreen: bool,
+    pub mute: bool,
+    pub input: Option<String>,
+    pub frames: Option<u64>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            rom: String::new(),
+            scale: 2,
+            debug: true,
+            font: None,
+            region: None,
+            fullscreen: false,
+            mute: false,
+            input: None,
+            frames: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HeadlessOptions {
+    pub rom: String,
+    pub region: Option<Region>,
+    pub frames: u64,
+    pub until: Option<Condition>,
+    pub script: Option<String>,
+    pub frame_out: Option<String>,
+    pub audio_out: Option<String>,
+    pub ram_out: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Command {
+    Run(Options),
+    Headless(HeadlessOptions),
+    Help,
+}
+
+/* A mistake on the command line, printed above the usage text */
+#[derive(Debug)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error: {}\n\n{}", self.0, USAGE)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+/* One `--flag`, `--flag value` or `--flag=value` at a time, plus positional arguments.
+ * `--` ends option parsing. */
+struct Args<I: Iterator<Item = String>> {
+    args: I,
+    inline: Option<String>,
+    only_positional: bool,
+}
+
+enum Arg {
+    Flag(String),
+    Positional(String),
+}
+
+impl<I: Iterator<Item = String>> Args<I> {
+    fn next(&mut self) -> Result<Option<Arg>, UsageError> {
+        if let Some(value) = self.inline.take() {
+            return Err(UsageError(format!("unexpected value `{}`", value)));
+        }
+
+        let arg = match self.args.next() {
+            Some(a) => a,
+            None => return Ok(None),
+        };
+
+        if arg == "--" && !self.only_positional {
+            self.only_positional = true;
+            return self.next();
+        }
+        if self.only_positional || !arg.starts_with('-') || arg == "-" {
+            return Ok(Some(Arg::Positional(arg)));
+        }
+
+        match arg.split_once('=') {
+            Some((flag, value)) => {
+                self.inline = Some(value.to_string());
+                Ok(Some(Arg::Flag(flag.to_string())))
+            },
+            None => Ok(Some(Arg::Flag(arg))),
+        }
+    }
+
+    fn value(&mut self, flag: &str) -> Result<String, UsageError> {
+        match self.inline.take().or_else(|| self.args.next()) {
+            Some(v) => Ok(v),
+            None => Err(UsageError(format!("{} needs a value", flag))),
+        }
+    }
+
+    fn frames(&mut self, flag: &str) -> Result<u64, UsageError> {
+        let v = self.value(flag)?;
+        match v.parse::<u64>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(UsageError(format!("{} must be a positive number, found `{}`", flag, v))),
+        }
+    }
+
+    /* A flag that takes no value must not have been given one with '=' */
+    fn switch(&mut self, flag: &str) -> Result<(), UsageError> {
+        match self.inline.take() {
+            Some(_) => Err(UsageError(format!("{} does not take a value", flag))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn set_rom(rom: &mut Option<String>, arg: String) -> Result<(), UsageError> {
+    if rom.is_some() {
+        return Err(UsageError(format!("unexpected argument `{}`, only one ROM can be run", arg)));
+    }
+    *rom = Some(arg);
+    Ok(())
+}
+
+/* Parses the arguments after the program name. Options may come before or after the ROM. */
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Command, UsageError> {
+    let mut args = args.into_iter().peekable();
+    if args.peek().is_some_and(|a| a == "headless") {
+        args.next();
+        return parse_headless(Args { args, inline: None, only_positional: false }, HeadlessOptions::default());
+    }
+
+    let mut args = Args { args, inline: None, only_positional: false };
+    let mut options = Options::default();
+    let mut rom = None;
+    let mut headless = false;
+    let mut gui_flag = None;
+
+    while let Some(arg) = args.next()? {
+        let flag = match arg {
+            Arg::Positional(a) => {
+                set_rom(&mut rom, a)?;
+                continue;
+            },
+            Arg::Flag(f) => f,
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--scale" => {
+                let v = args.value(&flag)?;
+                options.scale = match v.parse::<u32>() {
+                    Ok(s) if (1..=MAX_SCALE).contains(&s) => s,
+                    _ => return Err(UsageError(format!("--scale must be from 1 to {}, found `{}`", MAX_SCALE, v))),
+                };
+            },
+            "--region" => options.region = Some(args.value(&flag)?.parse().map_err(UsageError)?),
+            "--input" => options.input = Some(args.value(&flag)?),
+            "--font" => options.font = Some(args.value(&flag)?),
+            "--frames" => options.frames = Some(args.frames(&flag)?),
+            "--no-debug" => options.debug = false,
+            "--fullscreen" => options.fullscreen = true,
+            "--mute" => options.mute = true,
+            "--headless" => headless = true,
+            _ => return Err(UsageError(format!("unknown option `{}`", flag))),
+        }
+        args.switch(&flag)?;
+
+        if GUI_ONLY.contains(&flag.as_str()) {
+            gui_flag.get_or_insert(flag);
+        }
+    }
+
+    options.rom = match rom {
+        Some(r) => r,
+        None => return Err(UsageError("no ROM given".to_string())),
+    };
+
+    if headless {
+        if let Some(flag) = gui_flag {
+            return Err(UsageError(format!("{} has no effect with --headless", flag)));
+        }
+        return match options.frames {
+            Some(frames) => Ok(Command::Headless(HeadlessOptions {
+                rom: options.rom,
+                region: options.region,
+                frames,
+                ..HeadlessOptions::default()
+            })),
+            None => Err(UsageError("--headless needs --frames to know when to stop".to_string())),
+        };
+    }
+
+    Ok(Command::Run(options))
+}
+
+fn parse_headless<I: Iterator<Item = String>>(mut args: Args<I>, mut options: HeadlessOptions) -> Result<Command, UsageError> {
+    let mut rom = None;
+
+    while let Some(arg) = args.next()? {
+        let flag = match arg {
+            Arg::Positional(a) => {
+                set_rom(&mut rom, a)?;
+                continue;
+            },
+            Arg::Flag(f) => f,
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--frames" => options.frames = args.frames(&flag)?,
+            "--until" => options.until = Some(args.value(&flag)?.parse().map_err(UsageError)?),
+            "--script" => options.script = Some(args.value(&flag)?),
+            "--region" => options.region = Some(args.value(&flag)?.parse().map_err(UsageError)?),
+            "--frame-out" => options.frame_out = Some(args.value(&flag)?),
+            "--audio-out" => options.audio_out = Some(args.value(&flag)?),
+            "--ram-out" => options.ram_out = Some(args.value(&flag)?),
+            _ => return Err(UsageError(format!("unknown option `{}`", flag))),
+        }
+        args.switch(&flag)?;
+    }
+
+    options.rom = match rom {
+        Some(r) => r,
+        None => return Err(UsageError("no ROM given".to_string())),
+    };
+    if options.frames == 0 {
+        return Err(UsageError("headless runs need --frames to know when to stop".to_string()));
+    }
+
+    Ok(Command::Headless(options))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,131 @@
+use nes::region::Region;
+
+use super::{parse, Command, HeadlessOptions, Options, UsageError, USAGE};
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(str::to_string).collect()
+}
+
+fn run(line: &str) -> Options {
+    match parse(args(line)) {
+        Ok(Command::Run(options)) => options,
+        other => panic!("`{}` gave {:?}", line, other),
+    }
+}
+
+fn headless(line: &str) -> HeadlessOptions {
+    match parse(args(line)) {
+        Ok(Command::Headless(options)) => options,
+        other => panic!("`{}` gave {:?}", line, other),
+    }
+}
+
+fn error(line: &str) -> String {
+    match parse(args(line)) {
+        Err(UsageError(message)) => message,
+        other => panic!("`{}` should not parse, gave {:?}", line, other),
+    }
+}
+
+/* Run{{{1*/
+#[test]
+fn positional_rom_with_defaults() {
+    let options = run("game.nes");
+    assert_eq!(options.rom, "game.nes");
+    assert_eq!(options.scale, 2);
+    assert!(options.debug && !options.fullscreen && !options.mute);
+    assert_eq!(options.region, None);
+    assert_eq!(options.frames, None);
+}
+
+#[test]
+fn options_go_before_or_after_the_rom() {
+    let options = run("--mute game.nes --no-debug --region=pal --input keys.cfg");
+    assert_eq!(options.rom, "game.nes");
+    assert!(options.mute && !options.debug);
+    assert_eq!(options.region, Some(Region::Pal));
+    assert_eq!(options.input.as_deref(), Some("keys.cfg"));
+
+    /* After `--` even a name starting with '-' is the ROM */
+    assert_eq!(run("--fullscreen -- -game.nes").rom, "-game.nes");
+}
+
+#[test]
+fn only_one_rom() {
+    assert_eq!(error("a.nes b.nes"), "unexpected argument `b.nes`, only one ROM can be run");
+    assert_eq!(error("--mute"), "no ROM given");
+}
+
+#[test]
+fn scale_is_bounded() {
+    assert_eq!(run("--scale 1 game.nes").scale, 1);
+    assert_eq!(run("--scale=8 game.nes").scale, 8);
+
+    for bad in ["0", "9", "two", "-1"] {
+        assert_eq!(error(&format!("--scale {} game.nes", bad)), format!("--scale must be from 1 to 8, found `{}`", bad));
+    }
+    assert_eq!(error("game.nes --scale"), "--scale needs a value");
+}
+
+#[test]
+fn unknown_flags_are_usage_errors() {
+    assert_eq!(error("--turbo game.nes"), "unknown option `--turbo`");
+    assert_eq!(error("--mute=yes game.nes"), "--mute does not take a value");
+    assert_eq!(error("--region mars game.nes"), "unknown region `mars`, expected ntsc, pal or dendy");
+
+    /* Printed with the usage text below it */
+    let message = parse(args("-x game.nes")).unwrap_err().to_string();
+    assert!(message.starts_with("error: unknown option `-x`\n\n"));
+    assert!(message.ends_with(USAGE));
+}
+
+#[test]
+fn help_wins() {
+    assert!(matches!(parse(args("--scale 3 --help")), Ok(Command::Help)));
+    assert!(matches!(parse(args("headless -h")), Ok(Command::Help)));
+}
+//}}}1
+
+/* Headless{{{1*/
+#[test]
+fn headless_flag_needs_frames() {
+    let options = headless("--headless --frames 120 --region dendy game.nes");
+    assert_eq!(options.rom, "game.nes");
+    assert_eq!(options.frames, 120);
+    assert_eq!(options.region, Some(Region::Dendy));
+    assert!(options.until.is_none() && options.script.is_none());
+
+    assert_eq!(error("--headless game.nes"), "--headless needs --frames to know when to stop");
+    assert_eq!(error("--headless --frames 0 game.nes"), "--frames must be a positive number, found `0`");
+}
+
+#[test]
+fn headless_flag_rejects_window_and_audio_options() {
+    for flag in ["--scale 3", "--no-debug", "--font f.ttf", "--fullscreen", "--mute", "--input keys.cfg"] {
+        let name = flag.split_whitespace().next().unwrap();
+        assert_eq!(
+            error(&format!("--headless --frames 10 {} game.nes", flag)),
+            format!("{} has no effect with --headless", name),
+        );
+    }
+
+    /* They still work without it */
+    assert_eq!(run("--frames 10 --mute game.nes").frames, Some(10));
+}
+
+#[test]
+fn headless_command() {
+    let options = headless("headless game.nes --frames 600 --until 6000!=80 --script in.txt --frame-out f.ppm \
+        --audio-out a.wav --ram-out r.bin");
+    assert_eq!(options.frames, 600);
+    assert_eq!(options.until.map(|c| c.to_string()).as_deref(), Some("$6000 != $80"));
+    assert_eq!(options.script.as_deref(), Some("in.txt"));
+    assert_eq!(options.frame_out.as_deref(), Some("f.ppm"));
+    assert_eq!(options.audio_out.as_deref(), Some("a.wav"));
+    assert_eq!(options.ram_out.as_deref(), Some("r.bin"));
+    assert_eq!(options.region, None);
+
+    assert_eq!(error("headless game.nes"), "headless runs need --frames to know when to stop");
+    assert_eq!(error("headless --frames 10 --scale 2 game.nes"), "unknown option `--scale`");
+}
+//}}}1
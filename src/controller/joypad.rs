@@ -0,0 +1,91 @@
+pub const CONTROLLER_PORTS: usize = 2;
+
+/* In the order the shift register reports them */
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Button {
+    A,
+    B,
+    Select,
+    Start,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Button {
+    pub const ALL: [Button; 8] = [
+        Button::A, Button::B, Button::Select, Button::Start,
+        Button::Up, Button::Down, Button::Left, Button::Right,
+    ];
+
+    #[inline(always)]
+    pub fn mask(self) -> u8 {
+        1 << (self as u8)
+    }
+}
+
+/* The standard controller: a 4021 shift register that is parallel-loaded from the
+ * buttons while the strobe written to $4016 is high, and shifted out one bit per
+ * read of $4016/$4017 once it is low. */
+#[derive(Default)]
+pub struct Joypad {
+    pub buttons: u8,
+    strobe: bool,
+    shift: u8,
+    reads: u8,
+}
+
+impl Joypad {
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        if pressed {
+            self.set_buttons(self.buttons | button.mask());
+        }
+        else {
+            self.set_buttons(self.buttons & !button.mask());
+        }
+    }
+
+    /* Every button at once, as a mask of Button::mask() bits */
+    pub fn set_buttons(&mut self, buttons: u8) {
+        self.buttons = buttons;
+        if self.strobe {
+            self.reload();
+        }
+    }
+
+    pub fn write(&mut self, byte: u8) {
+        self.strobe = byte & 0x01 != 0;
+        if self.strobe {
+            self.reload();
+        }
+    }
+
+    /* Bit 0 of the controller's output; the caller supplies the open-bus bits */
+    pub fn read(&mut self) -> u8 {
+        let bit = self.peek();
+        if !self.strobe && self.reads < 8 {
+            self.shift >>= 1;
+            self.reads += 1;
+        }
+        bit
+    }
+
+    /* Once all eight buttons are out, an official pad keeps returning 1 */
+    pub fn peek(&self) -> u8 {
+        if self.strobe {
+            self.buttons & 0x01
+        }
+        else if self.reads >= 8 {
+            1
+        }
+        else {
+            self.shift & 0x01
+        }
+    }
+
+    fn reload(&mut self) {
+        self.shift = self.buttons;
+        self.reads = 0;
+    }
+}
@@ -0,0 +1,7 @@
+mod joypad;
+
+pub use joypad::{
+    Joypad,
+    Button,
+    CONTROLLER_PORTS,
+};
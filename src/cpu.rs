@@ -1,134 +1,75 @@
-use std::cmp::Ordering;
 use crate::register::*;
 
-pub fn branch(pc: &mut u16, offset: u8) {
-    let offset: i8 = if offset & 0x80  == 0x80 { -128 } else { 0 } + ((offset & 0x7f) as i8);
-    *pc += offset as u16;
-}
+/* Returns the cycles a taken branch adds: one, plus one more when the target is on another page */
+pub fn branch(pc: &mut u16, offset: u8) -> u8 {
+    let target = pc.wrapping_add(offset as i8 as u16);
+    let penalty = if (target & 0xff00) != (*pc & 0xff00) { 2 } else { 1 };
 
-pub fn shift_right(status: &mut StatusRegister, target: &mut u8) {
-    let mut set = Status::Mix(0);
-    let mut clear = Status::Carry | Status::Zero | Status::Negative;
+    *pc = target;
+    penalty
+}
 
-    if *target & 1 == 1 {
-        set |= Status::Carry;
-        clear ^= Status::Carry;
+/* Adds N and Z for `result` to the pending set/clear masks */
+fn zero_negative(set: &mut Status, clear: &mut Status, result: u8) {
+    if result & 0x80 == 0x80 {
+        *set |= Status::Negative;
+        *clear ^= Status::Negative;
     }
-
-    *target >>= 1;
-
-    if *target == 0 {
-        set |= Status::Zero;
-        clear ^= Status::Zero;
+    if result == 0 {
+        *set |= Status::Zero;
+        *clear ^= Status::Zero;
     }
-
-    status.set_flag(set);
-    status.clear_flag(clear);
 }
 
-pub fn shift_left(status: &mut StatusRegister, target: &mut u8) {
+/* Shifts and rotates: C takes the bit shifted out, N and Z follow the result */
+fn shift_flags(status: &mut StatusRegister, carry: bool, result: u8) {
     let mut set = Status::Mix(0);
     let mut clear = Status::Carry | Status::Zero | Status::Negative;
 
-    if *target & 0x80 == 0x80 {
+    if carry {
         set |= Status::Carry;
         clear ^= Status::Carry;
     }
+    zero_negative(&mut set, &mut clear, result);
 
-    *target <<= 1;
-
-    if *target & 0x80 == 0x80 {
-        set |= Status::Negative;
-        clear ^= Status::Negative;
-    }
-    else if *target == 0 {
-        set |= Status::Zero;
-        clear ^= Status::Zero;
-    }
-
-    status.set_flag(set);
     status.clear_flag(clear);
+    status.set_flag(set);
 }
 
-pub fn rotate_right(status: &mut StatusRegister, target: &mut u8) {
-    let mut set = Status::Mix(0);
-    let mut clear = Status::Carry | Status::Zero | Status::Negative;
-
-    if *target & 1 == 1 {
-        set |= Status::Carry | Status::Negative;
-        clear ^= Status::Carry | Status::Negative;
-    }
-    else if *target == 0 {
-        set |= Status::Zero;
-        clear ^= Status::Zero;
-    }
-
+pub fn shift_right(status: &mut StatusRegister, target: &mut u8) {
+    let carry = *target & 1 == 1;
     *target >>= 1;
-
-    status.set_flag(set);
-    status.clear_flag(clear);
-
-    if status.contains(Status::Carry) {
-        *target |= 0x80;
-    }
+    shift_flags(status, carry, *target);
 }
 
-pub fn rotate_left(status: &mut StatusRegister, target: &mut u8) {
-    let mut set = Status::Mix(0);
-    let mut clear = Status::Carry | Status::Zero | Status::Negative;
-
-    if *target & 0x80 == 0x80 {
-        set |= Status::Carry | Status::Negative;
-        clear ^= Status::Carry | Status::Negative;
-    }
-    else if *target == 0 {
-        set |= Status::Zero;
-        clear ^= Status::Zero;
-    }
-
+pub fn shift_left(status: &mut StatusRegister, target: &mut u8) {
+    let carry = *target & 0x80 == 0x80;
     *target <<= 1;
-
-    status.set_flag(set);
-    status.clear_flag(clear);
-
-    if status.contains(Status::Carry) {
-        *target |= 1;
-    }
+    shift_flags(status, carry, *target);
 }
 
-pub fn format_bcd(val: u8) -> u8 {
-    let mut nibble_low = val & 0xf;
-    let mut nibble_high = val >> 4;
-
-    if nibble_low > 9 {
-        nibble_low -= 10;
-        nibble_high += 1;
-    }
-    if nibble_high > 9 {
-        nibble_high -= 10;
-    }
+pub fn rotate_right(status: &mut StatusRegister, target: &mut u8) {
+    let carry = *target & 1 == 1;
+    *target = (*target >> 1) | (status.flag_value(Status::Carry) << 7);
+    shift_flags(status, carry, *target);
+}
 
-    (nibble_high << 4) | nibble_low
+pub fn rotate_left(status: &mut StatusRegister, target: &mut u8) {
+    let carry = *target & 0x80 == 0x80;
+    *target = (*target << 1) | status.flag_value(Status::Carry);
+    shift_flags(status, carry, *target);
 }
 
+/* CMP, CPX and CPY: C is set when no borrow occurs, N and Z follow reg - val */
 pub fn compare_u8(status: &mut StatusRegister, reg: u8, val: u8) {
     let mut set = Status::Mix(0);
     let mut clear = Status::Carry | Status::Zero | Status::Negative;
 
-    match reg.cmp(&val) {
-        Ordering::Less => {
-            set |= Status::Negative;
-            clear ^= Status::Negative;
-        },
-        Ordering::Equal => {
-            set |= Status::Zero | Status::Carry;
-            clear ^= Status::Zero | Status::Carry;
-        },
-        Ordering::Greater => {
-            set |= Status::Zero;
-            clear ^= Status::Zero;
-        },
-    };
+    if reg >= val {
+        set |= Status::Carry;
+        clear ^= Status::Carry;
+    }
+    zero_negative(&mut set, &mut clear, reg.wrapping_sub(val));
 
     status.clear_flag(clear);
     status.set_flag(set);
@@ -139,15 +80,7 @@ pub fn load_u8_memory(status: &mut StatusRegister, mem: &mut u8, val: u8) {
     let mut clear = Status::Negative | Status::Zero;
 
     *mem = val;
-
-    if val & 0x80 == 0x80 {
-        set |= Status::Negative;
-        clear ^= Status::Negative;
-    }
-    else if val == 0 {
-        set |= Status::Zero;
-        clear ^= Status::Zero;
-    }
+    zero_negative(&mut set, &mut clear, val);
 
     status.clear_flag(clear);
     status.set_flag(set);
@@ -0,0 +1,96 @@
+/* Imports{{{1*/
+use std::path::Path;
+
+use crate::{
+    cartridge::CartridgeError,
+    controller::{Joypad, CONTROLLER_PORTS},
+    memory::Bus,
+    processor::Processor,
+    region::Region,
+};
+//}}}1
+
+/* The console as a whole, driven one video frame at a time. Every frontend runs the
+ * machine through run_frame() so they all see the same timing. */
+pub struct Emulator {
+    pub processor: Processor<Bus>,
+    pub frames: u64,
+    pub cycles: u64,
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Emulator {
+    pub fn new() -> Self {
+        Emulator {
+            processor: Processor::<Bus>::new(),
+            frames: 0,
+            cycles: 0,
+        }
+    }
+
+    /* Inserts the cartridge and powers the console on */
+    pub fn load_rom<P: AsRef<Path>>(&mut self, path: P) -> Result<(), CartridgeError> {
+        self.processor.load_rom(path)?;
+        self.processor.power_on();
+
+        /* The PPU and APU run through the CPU's 7 reset cycles too, so the first frame
+         * starts where it does on hardware: 21 dots into line 0 on NTSC */
+        let cycles = self.processor.cycles as u32;
+        self.processor.bus.tick(cycles);
+        self.frames = 0;
+        self.cycles = 0;
+        Ok(())
+    }
+
+    pub fn region(&self) -> Region {
+        self.processor.bus.region()
+    }
+
+    /* Overrides the region load_rom() took from the cartridge header */
+    pub fn set_region(&mut self, region: Region) {
+        self.processor.bus.set_region(region);
+    }
+
+    pub fn reset(&mut self) {
+        self.processor.reset();
+    }
+
+    /* Runs until the PPU finishes a frame: 341 * 262 / 3, about 29780 2/3 CPU cycles on
+     * NTSC, or 29780.5 on average while rendering is on and odd frames skip a dot. PAL
+     * takes 33247.5 and Dendy 35464. The audio from the previous frame is dropped, so
+     * collect it with audio_samples() in between. Returns the cycles run. */
+    pub fn run_frame(&mut self) -> u32 {
+        self.processor.bus.apu.samples.clear();
+
+        let mut cycles = 0;
+        while !self.processor.bus.ppu.take_frame() {
+            cycles += self.processor.tick();
+        }
+
+        self.frames += 1;
+        self.cycles += cycles as u64;
+        cycles
+    }
+
+    /* Palette indices of the last finished frame */
+    pub fn frame(&self) -> &[u8] {
+        &self.processor.bus.ppu.frame[..]
+    }
+
+    /* The mixer's output for the last frame, one sample per CPU cycle */
+    pub fn audio_samples(&self) -> &[f32] {
+        &self.processor.bus.apu.samples
+    }
+
+    pub fn controllers(&mut self) -> &mut [Joypad; CONTROLLER_PORTS] {
+        &mut self.processor.bus.controllers
+    }
+}
+
+#[cfg(test)]
+mod tests;
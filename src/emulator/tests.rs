@@ -0,0 +1,67 @@
+use std::{env, fs, path::PathBuf, process};
+
+use super::Emulator;
+use crate::{
+    cartridge::test_image::TestImage,
+    region::Region,
+};
+
+const JMP: u8 = 0x4c;
+
+/* Writes an NROM image that spins on `JMP $8000` to a temporary file */
+fn spin_rom(name: &str, flags9: u8) -> PathBuf {
+    let image = TestImage::new(0, 1, 1)
+        .with_header_byte(9, flags9)
+        .with_prg(0, &[JMP, 0x00, 0x80])
+        .with_vectors(0x8000, 0x8000, 0x8000);
+
+    let path = env::temp_dir().join(format!("nes-emulator-{}-{}.nes", name, process::id()));
+    fs::write(&path, image.bytes()).unwrap();
+    path
+}
+
+fn load(name: &str, flags9: u8) -> Emulator {
+    let path = spin_rom(name, flags9);
+    let mut emulator = Emulator::new();
+    let loaded = emulator.load_rom(&path);
+    fs::remove_file(&path).unwrap();
+    loaded.unwrap();
+    emulator
+}
+
+#[test]
+fn reset_cycles_clock_the_ppu() {
+    let emulator = load("reset", 0);
+    let ppu = &emulator.processor.bus.ppu;
+    assert_eq!(emulator.processor.cycles, 7);
+    assert_eq!(emulator.processor.registers.pc, 0x8000);
+    assert_eq!((ppu.scanline, ppu.dot), (0, 21));
+}
+
+#[test]
+fn reset_cycles_follow_the_header_region() {
+    let emulator = load("reset-pal", 0x01);
+    let ppu = &emulator.processor.bus.ppu;
+    assert_eq!(emulator.region(), Region::Pal);
+    assert_eq!((ppu.scanline, ppu.dot), (0, 22));       /* 7 cycles at 3.2 dots each */
+}
+
+#[test]
+fn frames_average_29780_and_two_thirds_cycles() {
+    let mut emulator = load("frames", 0);
+
+    /* The first frame only runs from power-on to the first vblank */
+    emulator.run_frame();
+    assert_eq!(emulator.frames, 1);
+
+    /* Frames end on instruction boundaries, and JMP takes 3 cycles, so each one can be
+     * up to 2 cycles off as long as the next makes up for it */
+    let mut total = 0;
+    for _ in 0..30 {
+        let cycles = emulator.run_frame();
+        assert!((29778..=29783).contains(&cycles), "frame took {} cycles", cycles);
+        total += cycles;
+    }
+    assert!((30 * 29780 + 20 - 2..=30 * 29780 + 20 + 2).contains(&total), "30 frames took {} cycles", total);
+    assert_eq!(emulator.frames, 31);
+}
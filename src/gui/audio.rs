@@ -0,0 +1,125 @@
+use std::{
+    thread,
+    time::Duration,
+};
+
+use sdl2::{
+    Sdl,
+    audio::{AudioQueue, AudioSpecDesired},
+};
+
+use crate::{
+    apu::BlipBuffer,
+    region::Region,
+};
+
+pub const DEFAULT_SAMPLE_RATE: i32 = 44100;
+
+const DEVICE_BUFFER: u16 = 1024;            /* Samples per SDL callback */
+const TARGET_LATENCY: f64 = 0.05;           /* Seconds of audio to keep queued */
+const MAX_RATE_ADJUST: f64 = 0.005;         /* Largest pitch change rate control may apply */
+const HIGH_PASS: f32 = 0.996;               /* Pole of the DC-blocking filter */
+const VOLUME: f32 = 0.8;
+
+/* Feeds the APU's per-cycle samples to an SDL queue. The resampling ratio is nudged every
+ * frame so that the queue hovers around TARGET_LATENCY instead of slowly running dry or
+ * filling up when video and audio clocks disagree. */
+pub struct Audio {
+    queue: AudioQueue<f32>,
+    blip: BlipBuffer,
+    clock_rate: f64,            /* Input samples per second, one per CPU cycle */
+    sample_rate: f64,
+    last_level: f32,
+    filter_input: f32,
+    filter_output: f32,
+    samples: Vec<f32>,
+}
+
+impl Audio {
+    /* Opens the default device through whichever driver SDL picked; set SDL_AUDIODRIVER
+     * to "dummy" or "disk" to run without a sound card */
+    pub fn new(context: &Sdl, sample_rate: i32) -> Result<Audio, String> {
+        let subsystem = match context.audio() {
+            Ok(a) => a,
+            Err(e) => return Err(format!("Error initializing SDL2 Audio Subsystem: {}", e)),
+        };
+
+        let desired = AudioSpecDesired {
+            freq: Some(sample_rate),
+            channels: Some(1),
+            samples: Some(DEVICE_BUFFER),
+        };
+        let queue = match subsystem.open_queue::<f32, _>(None, &desired) {
+            Ok(q) => q,
+            Err(e) => return Err(format!("Error opening audio device: {}", e)),
+        };
+
+        let sample_rate = queue.spec().freq as f64;
+        let capacity = (sample_rate / 10.0) as usize;
+        let clock_rate = Region::default().cpu_frequency();     /* Until set_clock_rate says otherwise */
+        queue.resume();
+
+        Ok(Audio {
+            queue,
+            blip: BlipBuffer::new(clock_rate, sample_rate, capacity),
+            clock_rate,
+            sample_rate,
+            last_level: 0.0,
+            filter_input: 0.0,
+            filter_output: 0.0,
+            samples: Vec::with_capacity(capacity),
+        })
+    }
+
+    /* For consoles whose CPU does not run at the NTSC clock */
+    pub fn set_clock_rate(&mut self, clock_rate: f64) {
+        self.clock_rate = clock_rate;
+        self.blip.set_rates(clock_rate, self.sample_rate);
+    }
+
+    fn queued_samples(&self) -> f64 {
+        (self.queue.size() as usize / std::mem::size_of::<f32>()) as f64
+    }
+
+    /* Blocks until no more than TARGET_LATENCY is queued, so the device's clock paces
+     * the emulation instead of a timer */
+    pub fn wait_for_room(&self) {
+        let target = self.sample_rate * TARGET_LATENCY;
+        while self.queued_samples() > target {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /* Resamples one frame of APU output, one entry per CPU cycle, and queues it */
+    pub fn queue_frame(&mut self, levels: &[f32]) -> Result<(), String> {
+        let target = self.sample_rate * TARGET_LATENCY;
+        let queued = self.queued_samples();
+
+        /* Too far behind to catch up smoothly, e.g. after the window was dragged */
+        if queued > target * 4.0 {
+            self.queue.clear();
+        }
+
+        let fill = (queued / target).min(2.0);
+        let adjust = 1.0 + MAX_RATE_ADJUST * (1.0 - fill);
+        self.blip.set_rates(self.clock_rate, self.sample_rate * adjust);
+
+        for (time, &level) in levels.iter().enumerate() {
+            if level != self.last_level {
+                self.blip.add_delta(time as u32, level - self.last_level);
+                self.last_level = level;
+            }
+        }
+        self.blip.end_frame(levels.len() as u32);
+
+        self.samples.clear();
+        self.blip.read_samples(&mut self.samples);
+        for sample in self.samples.iter_mut() {
+            self.filter_output = *sample - self.filter_input + HIGH_PASS * self.filter_output;
+            self.filter_input = *sample;
+            *sample = self.filter_output * VOLUME;
+        }
+
+        self.queue.queue_audio(&self.samples)
+    }
+}
@@ -4,34 +4,86 @@ use sdl2::{
     video::Window,
     render::Canvas,
     rect::Rect,
+    rwops::RWops,
 };
 
 use std::process;
 
+use super::bitmap_font::{self, GLYPH_HEIGHT};
+
+const EMBEDDED_FONT: &[u8] = include_bytes!("../../fonts/ponde.ttf");
+const FONT_POINT_SIZE: u16 = 11;
+
+pub enum DebugFont<'a> {
+    Ttf(ttf::Font<'a, 'static>),
+    Bitmap { underline: bool },     /* SDL_ttf could not be used */
+}
+
 pub struct DebugWindow<'a> {
     pub width: u32,
     pub height: u32,
     pub start: u32,
-    pub font: ttf::Font<'a, 'static>,
+    pub font: DebugFont<'a>,
     pub line_height: u32,
     pub lines: u32,
 }
 
 impl<'a> DebugWindow<'a> {
-    pub fn new(width: u32, height: u32, start: u32, font_render: &'a ttf::Sdl2TtfContext, line_height: u32) -> Self {
-        let font = match font_render.load_font("/home/antpan/Documents/Programming/emu/nes/fonts/ponde.ttf", 11) {
-            Ok(f) => f,
-            Err(e) => {
-                eprintln!("Error loading font: {}", e);
-                process::exit(3);
-            }
+    /* Uses the font at `font_path` if given and loadable, then the copy of ponde.ttf built
+     * into the binary, then the bitmap font */
+    pub fn new(width: u32, height: u32, start: u32, font_render: Option<&'a ttf::Sdl2TtfContext>,
+        font_path: Option<&str>, line_height: u32) -> Self {
+
+        let font = match font_render {
+            Some(render) => DebugWindow::load_font(render, font_path),
+            None => DebugFont::Bitmap { underline: false },
         };
 
         DebugWindow { width, height, start, font, line_height, lines: (height / line_height) }
     }
 
+    fn load_font(font_render: &'a ttf::Sdl2TtfContext, font_path: Option<&str>) -> DebugFont<'a> {
+        if let Some(path) = font_path {
+            match font_render.load_font(path, FONT_POINT_SIZE) {
+                Ok(f) => return DebugFont::Ttf(f),
+                Err(e) => eprintln!("Warning: could not load font {}, using the built-in one\n{}", path, e),
+            }
+        }
+
+        let embedded = RWops::from_bytes(EMBEDDED_FONT)
+            .and_then(|rwops| font_render.load_font_from_rwops(rwops, FONT_POINT_SIZE));
+        match embedded {
+            Ok(f) => DebugFont::Ttf(f),
+            Err(e) => {
+                eprintln!("Warning: could not load the built-in font, using the bitmap font\n{}", e);
+                DebugFont::Bitmap { underline: false }
+            },
+        }
+    }
+
+    pub fn set_underline(&mut self, underline: bool) {
+        match &mut self.font {
+            DebugFont::Ttf(font) => font.set_style(if underline { ttf::FontStyle::UNDERLINE } else { ttf::FontStyle::NORMAL }),
+            DebugFont::Bitmap { underline: u } => *u = underline,
+        }
+    }
+
     pub fn render_line(&self, canvas: &mut Canvas<Window>, line: u32, text: String) {
-        let surface = match self.font.render(&text).solid(Color::WHITE) {
+        let font = match &self.font {
+            DebugFont::Ttf(f) => f,
+            DebugFont::Bitmap { underline } => {
+                /* Glyph pixels roughly as tall as the TTF's capitals */
+                let scale = (self.line_height / (GLYPH_HEIGHT + 5)).max(1);
+                let y = (self.line_height * line) as i32 + ((self.line_height - GLYPH_HEIGHT * scale) / 2) as i32;
+                if let Err(e) = bitmap_font::draw_text(canvas, 5 + self.start as i32, y, scale, &text, *underline) {
+                    eprintln!("Error displaying text: {}", e);
+                    process::exit(5);
+                }
+                return;
+            },
+        };
+
+        let surface = match font.render(&text).solid(Color::WHITE) {
             Ok(t) => t,
             Err(e) => {
                 eprintln!("Error rendering font: {}", e);
@@ -0,0 +1,363 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+use sdl2::{
+    Sdl,
+    GameControllerSubsystem,
+    controller::{
+        GameController,
+        Axis,
+        Button as PadButton,
+    },
+    event::Event,
+    keyboard::Keycode,
+};
+
+use crate::controller::{
+    Joypad,
+    Button,
+    CONTROLLER_PORTS,
+};
+
+pub const DEFAULT_DEADZONE: i16 = 8000;
+pub const DEFAULT_TURBO_RATE: u32 = 15;
+const MAX_TURBO_RATE: u32 = 30;     /* One press every other frame */
+const FRAME_RATE: u32 = 60;
+
+/* What a key or pad button does on the port it is bound to */
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Binding {
+    Button(Button),
+    Turbo(Button, u32),     /* Presses per second while held */
+}
+
+impl Binding {
+    /* Buttons held down during `frame` */
+    fn mask(self, frame: u32) -> u8 {
+        match self {
+            Binding::Button(button) => button.mask(),
+            Binding::Turbo(button, rate) if (frame * rate * 2 / FRAME_RATE).is_multiple_of(2) => button.mask(),
+            Binding::Turbo(..) => 0,
+        }
+    }
+}
+
+pub struct PortBindings {
+    pub keys: HashMap<Keycode, Binding>,
+    pub pad: HashMap<PadButton, Binding>,   /* For whichever pad is plugged into this port */
+    pub stick: bool,                        /* Left stick doubles as the d-pad */
+    pub deadzone: i16,
+}
+
+impl PortBindings {
+    fn empty() -> Self {
+        PortBindings { keys: HashMap::new(), pad: HashMap::new(), stick: true, deadzone: DEFAULT_DEADZONE }
+    }
+
+    fn default_pad() -> HashMap<PadButton, Binding> {
+        HashMap::from([
+            (PadButton::DPadUp, Binding::Button(Button::Up)),
+            (PadButton::DPadDown, Binding::Button(Button::Down)),
+            (PadButton::DPadLeft, Binding::Button(Button::Left)),
+            (PadButton::DPadRight, Binding::Button(Button::Right)),
+            (PadButton::A, Binding::Button(Button::B)),
+            (PadButton::B, Binding::Button(Button::A)),
+            (PadButton::X, Binding::Turbo(Button::B, DEFAULT_TURBO_RATE)),
+            (PadButton::Y, Binding::Turbo(Button::A, DEFAULT_TURBO_RATE)),
+            (PadButton::Back, Binding::Button(Button::Select)),
+            (PadButton::Start, Binding::Button(Button::Start)),
+        ])
+    }
+}
+
+/* Bindings for every port. Player 1 gets the arrows with Z/X for B/A and A/S for turbo,
+ * player 2 gets I/J/K/L with G/H for B/A, and both pads get the same layout. */
+pub struct InputConfig {
+    pub ports: [PortBindings; CONTROLLER_PORTS],
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        let mut one = PortBindings::empty();
+        one.keys = HashMap::from([
+            (Keycode::Up, Binding::Button(Button::Up)),
+            (Keycode::Down, Binding::Button(Button::Down)),
+            (Keycode::Left, Binding::Button(Button::Left)),
+            (Keycode::Right, Binding::Button(Button::Right)),
+            (Keycode::Z, Binding::Button(Button::B)),
+            (Keycode::X, Binding::Button(Button::A)),
+            (Keycode::A, Binding::Turbo(Button::B, DEFAULT_TURBO_RATE)),
+            (Keycode::S, Binding::Turbo(Button::A, DEFAULT_TURBO_RATE)),
+            (Keycode::RShift, Binding::Button(Button::Select)),
+            (Keycode::Return, Binding::Button(Button::Start)),
+        ]);
+        one.pad = PortBindings::default_pad();
+
+        let mut two = PortBindings::empty();
+        two.keys = HashMap::from([
+            (Keycode::I, Binding::Button(Button::Up)),
+            (Keycode::K, Binding::Button(Button::Down)),
+            (Keycode::J, Binding::Button(Button::Left)),
+            (Keycode::L, Binding::Button(Button::Right)),
+            (Keycode::G, Binding::Button(Button::B)),
+            (Keycode::H, Binding::Button(Button::A)),
+            (Keycode::T, Binding::Button(Button::Select)),
+            (Keycode::Y, Binding::Button(Button::Start)),
+        ]);
+        two.pad = PortBindings::default_pad();
+
+        InputConfig { ports: [one, two] }
+    }
+}
+
+impl InputConfig {
+    pub fn load(path: &str) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => InputConfig::parse(&text),
+            Err(e) => Err(format!("Error reading input config {}: {}", path, e)),
+        }
+    }
+
+    /* Changes the default bindings, one per line:
+     *      1.key.X = A             Keycode name, as SDL spells it
+     *      1.pad.y = A turbo 20    SDL's controller button name, turbo rate optional
+     *      2.key.Space = none      Removes a binding
+     *      1.stick = off
+     *      1.deadzone = 12000
+     * Ports count from 1 and anything after a '#' is ignored. */
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = InputConfig::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Err(e) = config.parse_line(line) {
+                return Err(format!("Input config line {}: {}", number + 1, e));
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let (target, value) = match line.split_once('=') {
+            Some((t, v)) => (t.trim(), v.trim()),
+            None => return Err(format!("expected `target = value`, found `{}`", line)),
+        };
+
+        let mut parts = target.splitn(3, '.');
+        let port = match parts.next().and_then(|p| p.parse::<usize>().ok()) {
+            Some(p) if (1..=CONTROLLER_PORTS).contains(&p) => p - 1,
+            _ => return Err(format!("`{}` does not start with a port from 1 to {}", target, CONTROLLER_PORTS)),
+        };
+        let bindings = &mut self.ports[port];
+
+        match (parts.next(), parts.next()) {
+            (Some("key"), Some(name)) => {
+                let key = match Keycode::from_name(name) {
+                    Some(k) => k,
+                    None => return Err(format!("unknown key `{}`", name)),
+                };
+                match parse_binding(value)? {
+                    Some(binding) => bindings.keys.insert(key, binding),
+                    None => bindings.keys.remove(&key),
+                };
+            },
+            (Some("pad"), Some(name)) => {
+                let button = match PadButton::from_string(name) {
+                    Some(b) => b,
+                    None => return Err(format!("unknown controller button `{}`", name)),
+                };
+                match parse_binding(value)? {
+                    Some(binding) => bindings.pad.insert(button, binding),
+                    None => bindings.pad.remove(&button),
+                };
+            },
+            (Some("stick"), None) => bindings.stick = match value {
+                "on" => true,
+                "off" => false,
+                _ => return Err(format!("stick is `on` or `off`, found `{}`", value)),
+            },
+            (Some("deadzone"), None) => bindings.deadzone = match value.parse::<i16>() {
+                Ok(d) if d >= 0 => d,
+                _ => return Err(format!("deadzone is from 0 to {}, found `{}`", i16::MAX, value)),
+            },
+            _ => return Err(format!("unknown setting `{}`", target)),
+        }
+
+        Ok(())
+    }
+}
+
+/* `A`, `A turbo` or `A turbo 20`; `none` unbinds */
+fn parse_binding(value: &str) -> Result<Option<Binding>, String> {
+    let mut words = value.split_whitespace();
+
+    let button = match words.next() {
+        Some("none") => return Ok(None),
+        Some(name) => match Button::ALL.iter().find(|b| format!("{:?}", b).eq_ignore_ascii_case(name)) {
+            Some(&b) => b,
+            None => return Err(format!("unknown NES button `{}`", name)),
+        },
+        None => return Err("missing button".to_string()),
+    };
+
+    let binding = match (words.next(), words.next()) {
+        (None, _) => Binding::Button(button),
+        (Some("turbo"), None) => Binding::Turbo(button, DEFAULT_TURBO_RATE),
+        (Some("turbo"), Some(rate)) => match rate.parse::<u32>() {
+            Ok(r) if (1..=MAX_TURBO_RATE).contains(&r) => Binding::Turbo(button, r),
+            _ => return Err(format!("turbo rate is from 1 to {} presses a second, found `{}`", MAX_TURBO_RATE, rate)),
+        },
+        (Some(word), _) => return Err(format!("unexpected `{}`", word)),
+    };
+
+    Ok(Some(binding))
+}
+
+/* Tracks what is held on the keyboard and on each port's pad, and turns it into button
+ * states once a frame. Pads claim the lowest free port when plugged in. */
+pub struct Input {
+    pub config: InputConfig,
+    subsystem: Option<GameControllerSubsystem>,
+    pads: [Option<GameController>; CONTROLLER_PORTS],
+    keys: HashSet<Keycode>,
+    pad_buttons: [HashSet<PadButton>; CONTROLLER_PORTS],
+    sticks: [(i16, i16); CONTROLLER_PORTS],
+    frame: u32,
+}
+
+impl Input {
+    /* Without the controller subsystem only the keyboard works */
+    pub fn new(context: &Sdl, config: InputConfig) -> Self {
+        let subsystem = match context.game_controller() {
+            Ok(s) => Some(s),
+            Err(e) => {
+                eprintln!("Warning: game controllers disabled\n{}", e);
+                None
+            },
+        };
+
+        Input {
+            config,
+            subsystem,
+            pads: Default::default(),
+            keys: HashSet::new(),
+            pad_buttons: Default::default(),
+            sticks: [(0, 0); CONTROLLER_PORTS],
+            frame: 0,
+        }
+    }
+
+    fn port_of(&self, instance: u32) -> Option<usize> {
+        self.pads.iter().position(|pad| pad.as_ref().is_some_and(|p| p.instance_id() == instance))
+    }
+
+    fn key_bound(&self, key: Keycode) -> bool {
+        self.config.ports.iter().any(|port| port.keys.contains_key(&key))
+    }
+
+    /* Returns whether the event was input for the console, so it should go no further */
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        match *event {
+            Event::KeyDown { keycode: Some(key), .. } if self.key_bound(key) => {
+                self.keys.insert(key);
+                true
+            },
+            Event::KeyUp { keycode: Some(key), .. } if self.key_bound(key) => {
+                self.keys.remove(&key);
+                true
+            },
+            Event::ControllerDeviceAdded { which, .. } => {
+                self.plug(which);
+                true
+            },
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if let Some(port) = self.port_of(which) {
+                    self.pads[port] = None;
+                    self.pad_buttons[port].clear();
+                    self.sticks[port] = (0, 0);
+                }
+                true
+            },
+            Event::ControllerButtonDown { which, button, .. } => {
+                if let Some(port) = self.port_of(which) {
+                    self.pad_buttons[port].insert(button);
+                }
+                true
+            },
+            Event::ControllerButtonUp { which, button, .. } => {
+                if let Some(port) = self.port_of(which) {
+                    self.pad_buttons[port].remove(&button);
+                }
+                true
+            },
+            Event::ControllerAxisMotion { which, axis, value, .. } => {
+                if let Some(port) = self.port_of(which) {
+                    match axis {
+                        Axis::LeftX => self.sticks[port].0 = value,
+                        Axis::LeftY => self.sticks[port].1 = value,
+                        _ => {},
+                    }
+                }
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn plug(&mut self, index: u32) {
+        let Some(subsystem) = &self.subsystem else { return };
+        let Some(port) = self.pads.iter().position(|pad| pad.is_none()) else {
+            eprintln!("Ignoring controller {}: every port has a pad", index);
+            return;
+        };
+
+        match subsystem.open(index) {
+            Ok(pad) => {
+                eprintln!("{} connected to port {}", pad.name(), port + 1);
+                self.pads[port] = Some(pad);
+            },
+            Err(e) => eprintln!("Error opening controller {}: {}", index, e),
+        }
+    }
+
+    /* Latches this frame's button states into the controllers */
+    pub fn update(&mut self, controllers: &mut [Joypad; CONTROLLER_PORTS]) {
+        for (port, joypad) in controllers.iter_mut().enumerate() {
+            let bindings = &self.config.ports[port];
+            let mut buttons = 0;
+
+            for key in &self.keys {
+                if let Some(binding) = bindings.keys.get(key) {
+                    buttons |= binding.mask(self.frame);
+                }
+            }
+            for button in &self.pad_buttons[port] {
+                if let Some(binding) = bindings.pad.get(button) {
+                    buttons |= binding.mask(self.frame);
+                }
+            }
+
+            if bindings.stick {
+                let (x, y) = self.sticks[port];
+                let deadzone = bindings.deadzone;
+                if x < -deadzone { buttons |= Button::Left.mask(); }
+                if x > deadzone { buttons |= Button::Right.mask(); }
+                if y < -deadzone { buttons |= Button::Up.mask(); }
+                if y > deadzone { buttons |= Button::Down.mask(); }
+            }
+
+            joypad.set_buttons(buttons);
+        }
+
+        self.frame = (self.frame + 1) % FRAME_RATE;
+    }
+}
+
+#[cfg(test)]
+mod tests;
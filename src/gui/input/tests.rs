@@ -0,0 +1,72 @@
+use sdl2::{controller::Button as PadButton, keyboard::Keycode};
+
+use super::{Binding, InputConfig, DEFAULT_TURBO_RATE};
+use crate::controller::Button;
+
+fn error(text: &str) -> String {
+    match InputConfig::parse(text) {
+        Ok(_) => panic!("`{}` should not parse", text),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn empty_config_keeps_the_defaults() {
+    let config = InputConfig::parse("# nothing here\n\n   \n").unwrap();
+    assert_eq!(config.ports[0].keys.get(&Keycode::Z), Some(&Binding::Button(Button::B)));
+    assert_eq!(config.ports[1].keys.get(&Keycode::H), Some(&Binding::Button(Button::A)));
+    assert_eq!(config.ports[0].pad.get(&PadButton::Y), Some(&Binding::Turbo(Button::A, DEFAULT_TURBO_RATE)));
+    assert!(config.ports[1].stick);
+}
+
+#[test]
+fn lines_change_bindings_and_settings() {
+    let config = InputConfig::parse("\
+        1.key.X = start         # was A\n\
+        1.key.Z = none\n\
+        2.key.Space = b turbo\n\
+        1.pad.y = A turbo 20\n\
+        2.stick = off\n\
+        2.deadzone = 12000\n").unwrap();
+
+    let one = &config.ports[0];
+    assert_eq!(one.keys.get(&Keycode::X), Some(&Binding::Button(Button::Start)));
+    assert_eq!(one.keys.get(&Keycode::Z), None);
+    assert_eq!(one.pad.get(&PadButton::Y), Some(&Binding::Turbo(Button::A, 20)));
+    assert!(one.stick);
+
+    let two = &config.ports[1];
+    assert_eq!(two.keys.get(&Keycode::Space), Some(&Binding::Turbo(Button::B, DEFAULT_TURBO_RATE)));
+    assert!(!two.stick);
+    assert_eq!(two.deadzone, 12000);
+}
+
+#[test]
+fn errors_name_the_line() {
+    assert_eq!(error("1.key.X = A\n3.key.X = A"),
+        "Input config line 2: `3.key.X` does not start with a port from 1 to 2");
+    assert_eq!(error("1.key.X A"), "Input config line 1: expected `target = value`, found `1.key.X A`");
+}
+
+#[test]
+fn bad_values_are_rejected() {
+    assert!(error("0.key.X = A").contains("port from 1 to 2"));
+    assert!(error("1.key.NoSuchKey = A").contains("unknown key `NoSuchKey`"));
+    assert!(error("1.pad.trigger = A").contains("unknown controller button `trigger`"));
+    assert!(error("1.key.X = C").contains("unknown NES button `C`"));
+    assert!(error("1.key.X =").contains("missing button"));
+    assert!(error("1.key.X = A turbo 31").contains("turbo rate is from 1 to 30"));
+    assert!(error("1.key.X = A slow").contains("unexpected `slow`"));
+    assert!(error("1.stick = maybe").contains("stick is `on` or `off`"));
+    assert!(error("1.deadzone = -1").contains("deadzone is from 0"));
+    assert!(error("1.mouse = on").contains("unknown setting `1.mouse`"));
+}
+
+#[test]
+fn turbo_alternates_at_its_rate() {
+    let held = |binding: Binding| (0..8).map(|frame| binding.mask(frame) != 0).collect::<Vec<_>>();
+
+    assert_eq!(held(Binding::Button(Button::A)), [true; 8]);
+    assert_eq!(held(Binding::Turbo(Button::A, 15)), [true, true, false, false, true, true, false, false]);
+    assert_eq!(held(Binding::Turbo(Button::A, 30)), [true, false, true, false, true, false, true, false]);
+}
@@ -1,6 +1,20 @@
 mod view;
 mod viewbuild;
 mod debug;
+mod bitmap_font;
+mod audio;
+mod input;
+mod pacer;
 pub use view::View;
 pub use viewbuild::ViewBuilder;
-pub use debug::DebugWindow;
+pub use debug::{DebugWindow, DebugFont};
+pub use audio::{Audio, DEFAULT_SAMPLE_RATE};
+pub use pacer::FramePacer;
+pub use input::{
+    Input,
+    InputConfig,
+    PortBindings,
+    Binding,
+    DEFAULT_DEADZONE,
+    DEFAULT_TURBO_RATE,
+};
@@ -0,0 +1,35 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+const MAX_LAG_FRAMES: u32 = 5;      /* Beyond this, give up catching up and resync */
+
+/* Sleeps between frames so they are shown at the console's own rate */
+pub struct FramePacer {
+    frame_time: Duration,
+    next: Instant,
+}
+
+impl FramePacer {
+    pub fn new(frame_rate: f64) -> Self {
+        FramePacer {
+            frame_time: Duration::from_secs_f64(1.0 / frame_rate),
+            next: Instant::now(),
+        }
+    }
+
+    /* Waits for the end of the current frame's time slot. Deadlines advance by the exact
+     * frame time rather than from when we woke, so oversleeping does not add up. */
+    pub fn wait(&mut self) {
+        self.next += self.frame_time;
+
+        let now = Instant::now();
+        if self.next > now {
+            thread::sleep(self.next - now);
+        }
+        else if now - self.next > self.frame_time * MAX_LAG_FRAMES {
+            self.next = now;
+        }
+    }
+}
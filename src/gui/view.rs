@@ -6,20 +6,34 @@ use std::{
 use sdl2::{
     Sdl,
     video::Window,
-    render::Canvas,
+    render::{Canvas, Texture},
+    pixels::PixelFormatEnum,
     EventPump,
     VideoSubsystem,
     event::Event,
     pixels::Color,
     rect::Rect,
     keyboard::Keycode,
-    ttf::FontStyle,
 };
 
 use crate::{
-    processor::Processor,
-    memory::Bus,
+    emulator::Emulator,
+    memory::{
+        Bus,
+        Readable,
+        ADDRESS_SPACE_LEN,
+    },
+    ppu::{
+        PALETTE,
+        SCREEN_WIDTH,
+        SCREEN_HEIGHT,
+    },
+};
+use super::{
     DebugWindow,
+    Audio,
+    Input,
+    FramePacer,
 };
 
 pub struct View<'a> {
@@ -28,6 +42,8 @@ pub struct View<'a> {
    pub canvas: Canvas<Window>,
    pub event: EventPump,
    pub debug: Option<DebugWindow<'a>>,
+   pub audio: Option<Audio>,
+   pub input: Input,
    pub scale: u32,
    pub width: u32,
    pub height: u32,
@@ -38,33 +54,53 @@ pub const LINES_MOVED_PER_STROKE: u32 = 8;
 pub const BYTES_MOVED_PER_STROKE: u32 = LINES_MOVED_PER_STROKE * 16;
 
 impl View<'_> {
-    pub fn event_loop(&mut self, processor: Processor<Bus>) {
+    /* Paced by the audio device when there is one, otherwise by a timer. Returns after
+     * `frames` frames if given, or when the window is closed. */
+    pub fn event_loop(&mut self, emulator: &mut Emulator, frames: Option<u64>) {
         let mut debug_mem_addr_start: u32 = 0;
         let mut debug_stack_addr_offset: u32 = 0;
         let lines = if let Some(debug_window) = &self.debug { debug_window.lines } else { 0 };
-        let lines_for_debug_mem = lines - 8;
+        let lines_for_debug_mem = lines.saturating_sub(8);
+
+        /* RGB888 ignores the top byte, so PALETTE entries can be copied in as they are */
+        let texture_creator = self.canvas.texture_creator();
+        let mut screen = match texture_creator.create_texture_streaming(PixelFormatEnum::RGB888,
+            SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32) {
+
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("Error creating screen texture: {}", e);
+                process::exit(2);
+            }
+        };
+        let mut pacer = FramePacer::new(emulator.region().frame_rate());
+        if let Some(audio) = &mut self.audio {
+            audio.set_clock_rate(emulator.region().cpu_frequency());
+        }
 
-        'program_active: loop {
+        'program_active: while frames.is_none_or(|n| emulator.frames < n) {
             let iter = self.event.poll_iter();
             for event in iter {
+                if self.input.handle_event(&event) {
+                    continue;
+                }
+
                 match event {
                     Event::Quit { .. } => break 'program_active,
-                    Event::KeyDown { keycode: Some(Keycode::Down), .. } => {
-                        if debug_mem_addr_start + (lines_for_debug_mem * BYTES_MOVED_PER_STROKE) < processor.bus.len {
-                            debug_mem_addr_start += BYTES_MOVED_PER_STROKE;
-                        }
+                    Event::KeyDown { keycode: Some(Keycode::PageDown), .. }
+                        if debug_mem_addr_start + (lines_for_debug_mem * BYTES_MOVED_PER_STROKE) < ADDRESS_SPACE_LEN => {
+                        debug_mem_addr_start += BYTES_MOVED_PER_STROKE;
                     },
-                    Event::KeyDown { keycode: Some(Keycode::Up), .. } => {
+                    Event::KeyDown { keycode: Some(Keycode::PageUp), .. } => {
                         if let Some(val) = debug_mem_addr_start.checked_sub(BYTES_MOVED_PER_STROKE) {
                             debug_mem_addr_start = val;
                         }
                     },
-                    Event::KeyDown { keycode: Some(Keycode::Left), .. } => {
-                        if debug_stack_addr_offset + 64 < processor.bus.len {
-                            debug_stack_addr_offset += 16;
-                        }
+                    Event::KeyDown { keycode: Some(Keycode::Home), .. }
+                        if debug_stack_addr_offset + 64 < ADDRESS_SPACE_LEN => {
+                        debug_stack_addr_offset += 16;
                     },
-                    Event::KeyDown { keycode: Some(Keycode::Right), .. } => {
+                    Event::KeyDown { keycode: Some(Keycode::End), .. } => {
                         if let Some(val) = debug_stack_addr_offset.checked_sub(16) {
                             debug_stack_addr_offset = val;
                         }
@@ -73,28 +109,46 @@ impl View<'_> {
                 }
             }
 
+            self.input.update(emulator.controllers());
+            emulator.run_frame();
+            self.update_frame(emulator.frame());
+
+            match &mut self.audio {
+                Some(audio) => {
+                    audio.wait_for_room();
+                    if let Err(e) = audio.queue_frame(emulator.audio_samples()) {
+                        eprintln!("Error queueing audio: {}", e);
+                    }
+                },
+                None => pacer.wait(),
+            }
+
             self.reset_screen();
-            self.render();
-            self.debug_render(&processor.bus, debug_mem_addr_start, debug_stack_addr_offset);
+            self.render(&mut screen);
+            self.debug_render(&emulator.processor.bus, debug_mem_addr_start, debug_stack_addr_offset);
             self.canvas.present();
         }
     }
 
-    pub fn render(&mut self) {
-        // Render Main Screen
-        for i in 0..256 {
-            for j in 0..240 {
-                let data = self.frame[(i * 240) + j];
-                let r = (data & 0x00ff0000) >> 16;
-                let g = (data & 0x0000ff00) >> 8;
-                let b = data & 0x000000ff;
-                self.canvas.set_draw_color(Color::RGB(r as u8, g as u8, b as u8));
-                if let Err(e) = self.canvas.fill_rect(Rect::new(j as i32, i as i32, self.scale, self.scale)) {
-                    eprintln!("Error on screen render: {}", e);
-                    process::exit(2);
+    /* Uploads the frame to `screen` and lets the renderer scale it into place */
+    pub fn render(&mut self, screen: &mut Texture) {
+        let frame = &self.frame;
+        let upload = screen.with_lock(None, |pixels: &mut [u8], pitch: usize| {
+            for (row, line) in frame.chunks_exact(SCREEN_WIDTH).enumerate() {
+                let dest = &mut pixels[(row * pitch)..(row * pitch + SCREEN_WIDTH * 4)];
+                for (bytes, rgb) in dest.chunks_exact_mut(4).zip(line) {
+                    bytes.copy_from_slice(&rgb.to_ne_bytes());
                 }
-
             }
+        });
+        if let Err(e) = upload {
+            eprintln!("Error on screen render: {}", e);
+            process::exit(2);
+        }
+
+        if let Err(e) = self.canvas.copy(screen, None, Rect::new(0, 0, self.width, self.height)) {
+            eprintln!("Error on screen render: {}", e);
+            process::exit(2);
         }
     }
 
@@ -112,7 +166,6 @@ impl View<'_> {
 
             for i in 2..debug_window.lines-6 {
                 let slice_start = mem_addr + ((i - 2) * 16);
-                let slice = bus.as_slice(slice_start);
 
                 let mut text = String::new();
                 if let Err(e) = write!(text, "{:05X}: ", slice_start) {
@@ -120,27 +173,26 @@ impl View<'_> {
                     process::exit(11);
                 }
 
-                for j in slice.iter().take(16) {
-                    if let Err(e) = write!(text, "{:02x} ", j) {
+                for j in slice_start..(slice_start + 16) {
+                    if let Err(e) = write!(text, "{:02x} ", bus.peek(j as u16)) {
                         eprintln!("Error formatting debug text: {}", e);
                         process::exit(12);
                     }
                 }
 
                 if i == debug_window.lines-7 {
-                    debug_window.font.set_style(FontStyle::UNDERLINE);
+                    debug_window.set_underline(true);
                 }
-                debug_window.render_line(&mut self.canvas, i as u32, text);
-                debug_window.font.set_style(FontStyle::NORMAL);
+                debug_window.render_line(&mut self.canvas, i, text);
+                debug_window.set_underline(false);
             }
 
 
             // Dump Stack
-            debug_window.render_line(&mut self.canvas, debug_window.lines as u32 - 6, "Stack Dump".to_string());
-            debug_window.render_line(&mut self.canvas, debug_window.lines as u32 - 5, "------ 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F".to_string());
+            debug_window.render_line(&mut self.canvas, debug_window.lines - 6, "Stack Dump".to_string());
+            debug_window.render_line(&mut self.canvas, debug_window.lines - 5, "------ 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F".to_string());
             for i in (debug_window.lines-4)..debug_window.lines {
-                let slice_start = stack_addr as u32 + ((i + 4 - debug_window.lines) * 16);
-                let slice = bus.as_slice(slice_start);
+                let slice_start = stack_addr + ((i + 4 - debug_window.lines) * 16);
 
                 let mut text = String::new();
                 if let Err(e) = write!(text, "{:05X}: ", slice_start) {
@@ -148,18 +200,25 @@ impl View<'_> {
                     process::exit(11);
                 }
 
-                for j in slice.iter().take(16) {
-                    if let Err(e) = write!(text, "{:02x} ", j) {
+                for j in slice_start..(slice_start + 16) {
+                    if let Err(e) = write!(text, "{:02x} ", bus.peek(j as u16)) {
                         eprintln!("Error formatting debug text: {}", e);
                         process::exit(12);
                     }
                 }
 
-                debug_window.render_line(&mut self.canvas, i as u32, text);
+                debug_window.render_line(&mut self.canvas, i, text);
             }
         }
     }
 
+    /* Converts a frame of palette RAM values from the PPU into RGB */
+    pub fn update_frame(&mut self, pixels: &[u8]) {
+        for (rgb, &color) in self.frame.iter_mut().zip(pixels) {
+            *rgb = PALETTE[color as usize & 0x3f];
+        }
+    }
+
     pub fn reset_screen(&mut self) {
         self.canvas.set_draw_color(Color::BLACK);
         self.canvas.clear();
@@ -172,7 +231,7 @@ impl View<'_> {
     pub fn draw_square(&mut self, color: u32, byte: u32) {
         for i in 0..16 {
             for j in byte..(byte+16) {
-                self.frame[(i * 240) + j as usize] = color;
+                self.frame[(i * SCREEN_WIDTH) + j as usize] = color;
             }
         }
     }
@@ -1,4 +1,4 @@
-use super::{View, DebugWindow};
+use super::{View, DebugWindow, Audio, Input, InputConfig};
 
 use sdl2::{
     video::WindowBuildError,
@@ -12,12 +12,16 @@ pub struct ViewBuilder<'a> {
     width: u32,
     height: u32,
     scale: u32,
-    debug: Option<(&'a ttf::Sdl2TtfContext, u32)>,
+    debug: Option<(Option<&'a ttf::Sdl2TtfContext>, u32)>,
+    debug_font: Option<String>,
+    sample_rate: Option<i32>,
+    input: InputConfig,
+    fullscreen: bool,
 }
 
 impl Default for ViewBuilder<'_> {
     fn default() -> Self {
-        ViewBuilder { width: 256, height: 240, scale: 1, debug: None }
+        ViewBuilder { width: 256, height: 240, scale: 1, debug: None, debug_font: None, sample_rate: None, input: InputConfig::default(), fullscreen: false }
     }
 }
 
@@ -35,18 +39,40 @@ impl<'a> ViewBuilder<'a> {
         self
     }
 
-    pub fn as_debug(mut self, font_render: &'a ttf::Sdl2TtfContext, line_height: u32) -> Self {
+    /* Without a TTF context the panel falls back to the built-in bitmap font */
+    pub fn as_debug(mut self, font_render: Option<&'a ttf::Sdl2TtfContext>, line_height: u32) -> Self {
         self.debug = Some((font_render, line_height));
         self
     }
 
+    /* A TTF file to use in the debug panel instead of the bundled ponde.ttf */
+    pub fn with_debug_font(mut self, path: String) -> Self {
+        self.debug_font = Some(path);
+        self
+    }
+
+    pub fn with_audio(mut self, sample_rate: i32) -> Self {
+        self.sample_rate = Some(sample_rate);
+        self
+    }
+
+    pub fn with_input(mut self, input: InputConfig) -> Self {
+        self.input = input;
+        self
+    }
+
+    pub fn fullscreen(mut self) -> Self {
+        self.fullscreen = true;
+        self
+    }
+
     pub fn build(self) -> Result<View<'a>, String> {
         let mut window_width: u32 = self.width;
         let window_height: u32 = self.height;
         let debug_window = match self.debug {
             Some((render, line_height)) => {
                 window_width += DEBUG_WINDOW_WIDTH * self.scale;
-                Some(DebugWindow::new(DEBUG_WINDOW_WIDTH * self.scale, self.height, self.width, render, line_height))
+                Some(DebugWindow::new(DEBUG_WINDOW_WIDTH * self.scale, self.height, self.width, render, self.debug_font.as_deref(), line_height))
             },
             None => None,
         };
@@ -59,9 +85,12 @@ impl<'a> ViewBuilder<'a> {
             Ok(v) => v,
             Err(e) => return Err(format!("Error initializing SDL2 Video Subsystem: {}", e)),
         };
-        let window = match video.window("Demo", window_width, window_height)
-            .position_centered()
-            .build() {
+        let mut window = video.window("NES", window_width, window_height);
+        window.position_centered();
+        if self.fullscreen {
+            window.fullscreen_desktop();
+        }
+        let window = match window.build() {
 
             Ok(w) => w,
             Err(err) => match err {
@@ -83,7 +112,21 @@ impl<'a> ViewBuilder<'a> {
             Err(err) => return Err(format!("Error initializing Event Pump: {}", err)),
         };
 
+        /* Running silent beats not running at all */
+        let audio = match self.sample_rate {
+            Some(rate) => match Audio::new(&context, rate) {
+                Ok(a) => Some(a),
+                Err(e) => {
+                    eprintln!("Warning: continuing without sound\n{}", e);
+                    None
+                },
+            },
+            None => None,
+        };
+
         Ok(View {
+            audio,
+            input: Input::new(&context, self.input),
             context,
             canvas,
             event,
@@ -91,7 +134,7 @@ impl<'a> ViewBuilder<'a> {
             debug: debug_window,
             height: self.height,
             width: self.width,
-            scale: self.scale * 8,
+            scale: self.scale,
             frame: [0; 256 * 240]
         })
     }
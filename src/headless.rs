@@ -0,0 +1,296 @@
+/* Imports{{{1*/
+use std::{
+    fmt,
+    fs,
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{
+    apu::BlipBuffer,
+    controller::{Button, CONTROLLER_PORTS},
+    emulator::Emulator,
+    memory::{Readable, RAM_SIZE},
+    ppu::{PALETTE, SCREEN_WIDTH, SCREEN_HEIGHT},
+};
+//}}}1
+
+pub const WAV_SAMPLE_RATE: u32 = 44100;
+
+/* Input scripts{{{1*/
+/* From `frame` on, `port` holds exactly `buttons` until its next event */
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct InputEvent {
+    pub frame: u64,
+    pub port: usize,
+    pub buttons: u8,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct InputScript {
+    events: Vec<InputEvent>,
+}
+
+impl InputScript {
+    pub fn new(mut events: Vec<InputEvent>) -> Self {
+        events.sort_by_key(|e| e.frame);
+        InputScript { events }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        match fs::read_to_string(&path) {
+            Ok(text) => InputScript::parse(&text),
+            Err(e) => Err(format!("Error reading input script {}: {}", path.as_ref().display(), e)),
+        }
+    }
+
+    /* One event per line, `<frame> <port> <buttons>`, e.g.
+     *      60  1 Start
+     *      62  1 -             Releases everything
+     *      90  1 Right+B
+     * Frames count from 0, ports from 1, and anything after a '#' is ignored. */
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut events = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            match InputScript::parse_event(line) {
+                Ok(event) => events.push(event),
+                Err(e) => return Err(format!("Input script line {}: {}", number + 1, e)),
+            }
+        }
+
+        Ok(InputScript::new(events))
+    }
+
+    fn parse_event(line: &str) -> Result<InputEvent, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let [frame, port, buttons] = words[..] else {
+            return Err(format!("expected `<frame> <port> <buttons>`, found `{}`", line));
+        };
+
+        let frame = match frame.parse::<u64>() {
+            Ok(f) => f,
+            Err(_) => return Err(format!("`{}` is not a frame number", frame)),
+        };
+        let port = match port.parse::<usize>() {
+            Ok(p) if (1..=CONTROLLER_PORTS).contains(&p) => p - 1,
+            _ => return Err(format!("port is from 1 to {}, found `{}`", CONTROLLER_PORTS, port)),
+        };
+
+        let mut mask = 0;
+        if buttons != "-" {
+            for name in buttons.split('+') {
+                match Button::ALL.iter().find(|b| format!("{:?}", b).eq_ignore_ascii_case(name)) {
+                    Some(button) => mask |= button.mask(),
+                    None => return Err(format!("unknown button `{}`", name)),
+                }
+            }
+        }
+
+        Ok(InputEvent { frame, port, buttons: mask })
+    }
+}
+
+/* Stop conditions{{{1*/
+/* Compares a byte of the CPU address space, read without side effects */
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Condition {
+    Equals(u16, u8),
+    NotEquals(u16, u8),
+}
+
+impl Condition {
+    pub fn holds(&self, emulator: &Emulator) -> bool {
+        match *self {
+            Condition::Equals(addr, value) => emulator.processor.bus.peek(addr) == value,
+            Condition::NotEquals(addr, value) => emulator.processor.bus.peek(addr) != value,
+        }
+    }
+}
+
+/* `6000=80` or `$6000!=80`, both numbers in hex */
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, value, equals) = match s.split_once("!=") {
+            Some((a, v)) => (a, v, false),
+            None => match s.split_once('=') {
+                Some((a, v)) => (a, v, true),
+                None => return Err(format!("expected `ADDR=VALUE` or `ADDR!=VALUE`, found `{}`", s)),
+            },
+        };
+
+        let addr = addr.trim().trim_start_matches('$');
+        let value = value.trim().trim_start_matches('$');
+        let (addr, value) = match (u16::from_str_radix(addr, 16), u8::from_str_radix(value, 16)) {
+            (Ok(a), Ok(v)) => (a, v),
+            _ => return Err(format!("`{}` needs a hex address and a hex byte", s)),
+        };
+
+        Ok(if equals { Condition::Equals(addr, value) } else { Condition::NotEquals(addr, value) })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Equals(addr, value) => write!(f, "${:04X} = ${:02X}", addr, value),
+            Condition::NotEquals(addr, value) => write!(f, "${:04X} != ${:02X}", addr, value),
+        }
+    }
+}
+//}}}1
+
+pub struct Outcome {
+    pub frames: u64,
+    pub condition_met: bool,
+}
+
+struct Recorder {
+    blip: BlipBuffer,
+    last_level: f32,
+    samples: Vec<f32>,
+}
+
+/* Runs the console without a display, audio device or real input, for test suites and
+ * build machines. Everything observable is left in memory to inspect or write out. */
+pub struct Headless {
+    pub emulator: Emulator,
+    script: InputScript,
+    next_event: usize,
+    recorder: Option<Recorder>,
+}
+
+impl Headless {
+    pub fn new(emulator: Emulator) -> Self {
+        Headless { emulator, script: InputScript::default(), next_event: 0, recorder: None }
+    }
+
+    pub fn with_script(mut self, script: InputScript) -> Self {
+        self.script = script;
+        self.next_event = 0;
+        self
+    }
+
+    /* Keeps the audio, resampled to WAV_SAMPLE_RATE */
+    pub fn record_audio(mut self) -> Self {
+        let clock_rate = self.emulator.region().cpu_frequency();
+        let capacity = WAV_SAMPLE_RATE as usize / 10;
+        self.recorder = Some(Recorder {
+            blip: BlipBuffer::new(clock_rate, WAV_SAMPLE_RATE as f64, capacity),
+            last_level: 0.0,
+            samples: Vec::new(),
+        });
+        self
+    }
+
+    pub fn run_frame(&mut self) {
+        let frame = self.emulator.frames;
+        while let Some(event) = self.script.events.get(self.next_event).filter(|e| e.frame <= frame) {
+            self.emulator.controllers()[event.port].set_buttons(event.buttons);
+            self.next_event += 1;
+        }
+
+        self.emulator.run_frame();
+
+        if let Some(recorder) = &mut self.recorder {
+            let levels = self.emulator.audio_samples();
+            for (time, &level) in levels.iter().enumerate() {
+                if level != recorder.last_level {
+                    recorder.blip.add_delta(time as u32, level - recorder.last_level);
+                    recorder.last_level = level;
+                }
+            }
+            recorder.blip.end_frame(levels.len() as u32);
+            recorder.blip.read_samples(&mut recorder.samples);
+        }
+    }
+
+    /* Runs until `until` holds after a frame, or for `max_frames` frames */
+    pub fn run(&mut self, max_frames: u64, until: Option<Condition>) -> Outcome {
+        for frames in 1..=max_frames {
+            self.run_frame();
+            if until.is_some_and(|c| c.holds(&self.emulator)) {
+                return Outcome { frames, condition_met: true };
+            }
+        }
+
+        Outcome { frames: max_frames, condition_met: false }
+    }
+
+    /* The last frame as packed 8-bit RGB */
+    pub fn frame_rgb(&self) -> Vec<u8> {
+        self.emulator.frame().iter()
+            .flat_map(|&color| {
+                let rgb = PALETTE[color as usize & 0x3f];
+                [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]
+            })
+            .collect()
+    }
+
+    /* Empty unless record_audio() was used */
+    pub fn audio(&self) -> &[f32] {
+        match &self.recorder {
+            Some(recorder) => &recorder.samples,
+            None => &[],
+        }
+    }
+
+    pub fn ram(&self) -> &[u8; RAM_SIZE] {
+        self.emulator.processor.bus.ram()
+    }
+
+    /* Binary PPM, which nearly every image tool reads */
+    pub fn write_frame<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        write!(file, "P6\n{} {}\n255\n", SCREEN_WIDTH, SCREEN_HEIGHT)?;
+        file.write_all(&self.frame_rgb())?;
+        file.flush()
+    }
+
+    /* Mono 32-bit float WAV */
+    pub fn write_audio<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let samples = self.audio();
+        let data_len = (samples.len() * 4) as u32;
+
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        file.write_all(b"RIFF")?;
+        file.write_all(&(4 + 26 + 12 + 8 + data_len).to_le_bytes())?;
+        file.write_all(b"WAVE")?;
+
+        file.write_all(b"fmt ")?;
+        file.write_all(&18u32.to_le_bytes())?;
+        file.write_all(&3u16.to_le_bytes())?;                    /* IEEE float */
+        file.write_all(&1u16.to_le_bytes())?;                    /* Channels */
+        file.write_all(&WAV_SAMPLE_RATE.to_le_bytes())?;
+        file.write_all(&(WAV_SAMPLE_RATE * 4).to_le_bytes())?;   /* Bytes per second */
+        file.write_all(&4u16.to_le_bytes())?;                    /* Bytes per frame */
+        file.write_all(&32u16.to_le_bytes())?;                   /* Bits per sample */
+        file.write_all(&0u16.to_le_bytes())?;                    /* No extension */
+
+        file.write_all(b"fact")?;
+        file.write_all(&4u32.to_le_bytes())?;
+        file.write_all(&(samples.len() as u32).to_le_bytes())?;
+
+        file.write_all(b"data")?;
+        file.write_all(&data_len.to_le_bytes())?;
+        for sample in samples {
+            file.write_all(&sample.to_le_bytes())?;
+        }
+        file.flush()
+    }
+
+    pub fn write_ram<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.ram())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,122 @@
+use std::{env, fs, process};
+
+use super::{Condition, Headless, InputEvent, InputScript, WAV_SAMPLE_RATE};
+use crate::{
+    controller::Button,
+    emulator::Emulator,
+    memory::Writable,
+};
+
+fn script_error(text: &str) -> String {
+    match InputScript::parse(text) {
+        Ok(script) => panic!("`{}` should not parse, gave {:?}", text, script),
+        Err(e) => e,
+    }
+}
+
+/* Input scripts{{{1*/
+#[test]
+fn script_events_in_frame_order() {
+    let script = InputScript::parse("\
+        # Title screen\n\
+        \n\
+        60  1 Start\n\
+        90  2 Right+B   # held together\n\
+        62  1 -\n\
+        91  2 a+SELECT\n").unwrap();
+
+    assert_eq!(script.events, [
+        InputEvent { frame: 60, port: 0, buttons: Button::Start.mask() },
+        InputEvent { frame: 62, port: 0, buttons: 0 },
+        InputEvent { frame: 90, port: 1, buttons: Button::Right.mask() | Button::B.mask() },
+        InputEvent { frame: 91, port: 1, buttons: Button::A.mask() | Button::Select.mask() },
+    ]);
+}
+
+#[test]
+fn script_errors_name_the_line() {
+    assert_eq!(script_error("0 1 A\n10 3 A"), "Input script line 2: port is from 1 to 2, found `3`");
+    assert_eq!(script_error("10 0 A"), "Input script line 1: port is from 1 to 2, found `0`");
+    assert_eq!(script_error("# comment\n10 1 Turbo"), "Input script line 2: unknown button `Turbo`");
+    assert_eq!(script_error("10 1 A+"), "Input script line 1: unknown button ``");
+    assert_eq!(script_error("ten 1 A"), "Input script line 1: `ten` is not a frame number");
+    assert_eq!(script_error("10 1"), "Input script line 1: expected `<frame> <port> <buttons>`, found `10 1`");
+    assert_eq!(script_error("10 1 A B"), "Input script line 1: expected `<frame> <port> <buttons>`, found `10 1 A B`");
+}
+//}}}1
+
+/* Stop conditions{{{1*/
+#[test]
+fn condition_round_trips() {
+    let condition: Condition = "$6000!=80".parse().unwrap();
+    assert_eq!(condition, Condition::NotEquals(0x6000, 0x80));
+    assert_eq!(condition.to_string(), "$6000 != $80");
+    assert_eq!(condition.to_string().parse::<Condition>(), Ok(condition));
+
+    let condition: Condition = "f0=1".parse().unwrap();
+    assert_eq!(condition, Condition::Equals(0x00f0, 0x01));
+    assert_eq!(condition.to_string(), "$00F0 = $01");
+    assert_eq!(condition.to_string().parse::<Condition>(), Ok(condition));
+}
+
+#[test]
+fn condition_errors() {
+    assert_eq!("6000".parse::<Condition>(), Err("expected `ADDR=VALUE` or `ADDR!=VALUE`, found `6000`".to_string()));
+    for bad in ["6000=100", "10000=0", "$60g0=1", "=1", "6000="] {
+        assert_eq!(bad.parse::<Condition>(), Err(format!("`{}` needs a hex address and a hex byte", bad)));
+    }
+}
+
+#[test]
+fn condition_reads_the_bus() {
+    let mut emulator = Emulator::new();
+    emulator.processor.bus.write_byte(0x0012, 0x34);
+
+    assert!(Condition::Equals(0x0012, 0x34).holds(&emulator));
+    assert!(Condition::Equals(0x0812, 0x34).holds(&emulator));     /* Mirrored RAM */
+    assert!(!Condition::NotEquals(0x0012, 0x34).holds(&emulator));
+    assert!(Condition::NotEquals(0x0013, 0x34).holds(&emulator));
+}
+//}}}1
+
+/* Output files{{{1*/
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..(offset + 4)].try_into().unwrap())
+}
+
+#[test]
+fn wav_chunk_lengths() {
+    let samples = [0.5_f32, -0.25, 0.0];
+    let mut headless = Headless::new(Emulator::new()).record_audio();
+    headless.recorder.as_mut().unwrap().samples = samples.to_vec();
+
+    let path = env::temp_dir().join(format!("nes-headless-{}.wav", process::id()));
+    headless.write_audio(&path).unwrap();
+    let wav = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let data_len = 4 * samples.len();
+    assert_eq!(wav.len(), 8 + 4 + 26 + 12 + 8 + data_len);
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(u32_at(&wav, 4) as usize, 4 + 26 + 12 + 8 + data_len);
+    assert_eq!(&wav[8..12], b"WAVE");
+
+    assert_eq!(&wav[12..16], b"fmt ");
+    assert_eq!(u32_at(&wav, 16), 18);
+    assert_eq!(u32_at(&wav, 24), WAV_SAMPLE_RATE);
+
+    assert_eq!(&wav[38..42], b"fact");
+    assert_eq!(u32_at(&wav, 42), 4);
+    assert_eq!(u32_at(&wav, 46) as usize, samples.len());
+
+    assert_eq!(&wav[50..54], b"data");
+    assert_eq!(u32_at(&wav, 54) as usize, data_len);
+    assert_eq!(&wav[58..62], &0.5_f32.to_le_bytes());
+}
+
+#[test]
+fn audio_is_empty_unless_recorded() {
+    let headless = Headless::new(Emulator::new());
+    assert!(headless.audio().is_empty());
+}
+//}}}1
@@ -0,0 +1,14 @@
+pub mod processor;
+pub mod opcode;
+pub mod register;
+pub mod cpu;
+pub mod cartridge;
+pub mod mapper;
+pub mod gui;
+pub mod memory;
+pub mod ppu;
+pub mod apu;
+pub mod controller;
+pub mod emulator;
+pub mod region;
+pub mod headless;
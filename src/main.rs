@@ -1,44 +1,131 @@
-pub mod processor;
-pub mod opcode;
-pub mod register;
-pub mod cpu;
-pub mod gui;
-pub mod memory;
+mod cli;
 
-use crate::memory::*;
+use std::{
+    env,
+    process,
+};
 
-use std::process;
-
-use processor::*;
-use gui::*;
+use cli::{Command, Options, HeadlessOptions};
+use nes::{
+    emulator::Emulator,
+    headless::{Headless, InputScript},
+    gui::*,
+};
 
 fn main() {
-    let mut proc = Processor::<Bus>::new();
-    if let Err(e) = proc.load_rom() {
-        eprintln!("Error reading ROM into register: {}", e);
-        process::exit(5);
+    let options = match cli::parse(env::args().skip(1)) {
+        Ok(Command::Run(options)) => options,
+        Ok(Command::Headless(options)) => {
+            if let Err(e) = run_headless(options) {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+            return;
+        },
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        },
+    };
+
+    if let Err(e) = run(options) {
+        eprintln!("error: {}", e);
+        process::exit(1);
     }
+}
 
-    let font_render = match sdl2::ttf::init() {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!("Error initializing ttf::init(): {}", e);
-            process::exit(3);
+fn run(options: Options) -> Result<(), String> {
+    let mut emulator = Emulator::new();
+    if let Err(e) = emulator.load_rom(&options.rom) {
+        return Err(format!("{}: {}", options.rom, e));
+    }
+    if let Some(region) = options.region {
+        emulator.set_region(region);
+    }
+
+    let input = match &options.input {
+        Some(path) => InputConfig::load(path)?,
+        None => InputConfig::default(),
+    };
+
+    let font_render = if options.debug {
+        match sdl2::ttf::init() {
+            Ok(f) => Some(f),
+            Err(e) => {
+                eprintln!("Warning: SDL_ttf unavailable, using the bitmap font\n{}", e);
+                None
+            },
         }
+    }
+    else {
+        None
     };
 
-    let mut view = match ViewBuilder::default()
-        .with_scale(2)
-        .as_debug(&font_render, 16)
-        .build()
-    {
+    let mut builder = ViewBuilder::default()
+        .with_scale(options.scale)
+        .with_input(input);
+    if options.debug {
+        builder = builder.as_debug(font_render.as_ref(), 16);
+    }
+    if let Some(font) = options.font {
+        builder = builder.with_debug_font(font);
+    }
+    if !options.mute {
+        builder = builder.with_audio(DEFAULT_SAMPLE_RATE);
+    }
+    if options.fullscreen {
+        builder = builder.fullscreen();
+    }
 
+    let mut view = match builder.build() {
         Ok(v) => v,
-        Err(e) => {
-            eprintln!("FATAL: Error on building window\n{}", e);
-            process::exit(1);
-        }
+        Err(e) => return Err(format!("Error on building window\n{}", e)),
     };
 
-    view.event_loop(proc);
+    view.event_loop(&mut emulator, options.frames);
+    Ok(())
+}
+
+fn run_headless(options: HeadlessOptions) -> Result<(), String> {
+    let mut emulator = Emulator::new();
+    if let Err(e) = emulator.load_rom(&options.rom) {
+        return Err(format!("{}: {}", options.rom, e));
+    }
+    if let Some(region) = options.region {
+        emulator.set_region(region);
+    }
+
+    let mut headless = Headless::new(emulator);
+    if let Some(path) = &options.script {
+        headless = headless.with_script(InputScript::load(path)?);
+    }
+    if options.audio_out.is_some() {
+        headless = headless.record_audio();
+    }
+
+    let outcome = headless.run(options.frames, options.until);
+
+    if let Some(path) = &options.frame_out {
+        headless.write_frame(path).map_err(|e| format!("Error writing {}: {}", path, e))?;
+    }
+    if let Some(path) = &options.audio_out {
+        headless.write_audio(path).map_err(|e| format!("Error writing {}: {}", path, e))?;
+    }
+    if let Some(path) = &options.ram_out {
+        headless.write_ram(path).map_err(|e| format!("Error writing {}: {}", path, e))?;
+    }
+
+    match options.until {
+        Some(condition) if !outcome.condition_met =>
+            Err(format!("{} did not hold within {} frames", condition, outcome.frames)),
+        Some(condition) => {
+            println!("{} after {} frames", condition, outcome.frames);
+            Ok(())
+        },
+        None => Ok(()),
+    }
 }
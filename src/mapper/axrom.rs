@@ -0,0 +1,53 @@
+use super::{Mapper, has_bus_conflicts, write_chr_ram};
+use crate::cartridge::{Cartridge, Mirroring};
+
+const PRG_BANK_SIZE: usize = 0x8000;
+
+/* Mapper 7: switchable 32 KB PRG bank and software-selected single-screen mirroring */
+pub struct Axrom {
+    cartridge: Cartridge,
+    prg_bank: usize,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+}
+
+impl Axrom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let bus_conflicts = has_bus_conflicts(&cartridge);
+        Axrom { cartridge, prg_bank: 0, mirroring: Mirroring::SingleScreenLower, bus_conflicts }
+    }
+}
+
+impl Mapper for Axrom {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7fff => self.cartridge.read_prg_ram(addr),
+            0x8000..=0xffff => Some(self.cartridge.prg_bank(self.prg_bank, PRG_BANK_SIZE)[addr as usize & (PRG_BANK_SIZE - 1)]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x6000..=0x7fff => self.cartridge.write_prg_ram(addr, byte),
+            0x8000..=0xffff => {
+                let byte = if self.bus_conflicts { byte & self.cpu_read(addr).unwrap_or(0xff) } else { byte };
+                self.prg_bank = (byte & 0x07) as usize;
+                self.mirroring = if byte & 0x10 != 0 { Mirroring::SingleScreenUpper } else { Mirroring::SingleScreenLower };
+            },
+            _ => {},
+        }
+    }
+
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.cartridge.chr_bank(0, 0x2000)[addr as usize & 0x1fff]
+    }
+
+    fn ppu_write(&mut self, addr: u16, byte: u8) {
+        write_chr_ram(&mut self.cartridge, 0, addr, byte);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
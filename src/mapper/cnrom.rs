@@ -0,0 +1,49 @@
+use super::{Mapper, has_bus_conflicts, write_chr_ram};
+use crate::cartridge::{Cartridge, Mirroring};
+
+/* Mapper 3: fixed PRG like NROM, switchable 8 KB CHR ROM bank */
+pub struct Cnrom {
+    cartridge: Cartridge,
+    chr_bank: usize,
+    bus_conflicts: bool,
+}
+
+impl Cnrom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let bus_conflicts = has_bus_conflicts(&cartridge);
+        Cnrom { cartridge, chr_bank: 0, bus_conflicts }
+    }
+}
+
+impl Mapper for Cnrom {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7fff => self.cartridge.read_prg_ram(addr),
+            0x8000..=0xffff => Some(self.cartridge.prg_rom[(addr as usize - 0x8000) % self.cartridge.prg_rom.len()]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x6000..=0x7fff => self.cartridge.write_prg_ram(addr, byte),
+            0x8000..=0xffff => {
+                let byte = if self.bus_conflicts { byte & self.cpu_read(addr).unwrap_or(0xff) } else { byte };
+                self.chr_bank = byte as usize;
+            },
+            _ => {},
+        }
+    }
+
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.cartridge.chr_bank(self.chr_bank, 0x2000)[addr as usize & 0x1fff]
+    }
+
+    fn ppu_write(&mut self, addr: u16, byte: u8) {
+        write_chr_ram(&mut self.cartridge, self.chr_bank, addr, byte);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.cartridge.header.mirroring
+    }
+}
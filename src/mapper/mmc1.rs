@@ -0,0 +1,139 @@
+use super::Mapper;
+use crate::cartridge::{Cartridge, Mirroring};
+
+const PRG_BANK_SIZE: usize = 0x4000;
+const CHR_BANK_SIZE: usize = 0x1000;
+const OUTER_PRG_BANKS: usize = 16;  /* 256 KB; SUROM-style boards use CHR bit 4 to pick the half */
+
+/* Mapper 1: registers are loaded one bit at a time through a 5-bit serial port */
+pub struct Mmc1 {
+    cartridge: Cartridge,
+    shift: u8,
+    shift_count: u8,
+    control: u8,       /* CPPMM: CHR mode, PRG mode, mirroring */
+    chr_bank0: u8,
+    chr_bank1: u8,
+    prg_bank: u8,      /* RPPPP: PRG RAM disable, PRG bank */
+    written: bool,     /* A serial write landed during the current instruction */
+}
+
+impl Mmc1 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        Mmc1 {
+            cartridge,
+            shift: 0,
+            shift_count: 0,
+            control: 0x0c,
+            chr_bank0: 0,
+            chr_bank1: 0,
+            prg_bank: 0,
+            written: false,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x8000..=0x9fff => self.control = value,
+            0xa000..=0xbfff => self.chr_bank0 = value,
+            0xc000..=0xdfff => self.chr_bank1 = value,
+            _ => self.prg_bank = value,
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.prg_bank & 0x10 == 0
+    }
+
+    fn prg_bank_index(&self, addr: u16) -> usize {
+        let outer = if self.cartridge.prg_rom.len() > OUTER_PRG_BANKS * PRG_BANK_SIZE {
+            self.chr_bank0 as usize & 0x10
+        }
+        else {
+            0
+        };
+        let last = self.cartridge.prg_bank_count(PRG_BANK_SIZE).min(OUTER_PRG_BANKS) - 1;
+        let bank = self.prg_bank as usize & 0x0f;
+        let upper = addr >= 0xc000;
+
+        outer | match (self.control >> 2) & 0x03 {
+            0 | 1 => (bank & !1) | upper as usize,  /* 32 KB mode ignores the low bit */
+            2 => if upper { bank } else { 0 },      /* First bank fixed at $8000 */
+            _ => if upper { last } else { bank },   /* Last bank fixed at $C000 */
+        }
+    }
+
+    fn chr_bank_index(&self, addr: u16) -> usize {
+        let high = (addr >> 12) as usize & 1;
+        if self.control & 0x10 == 0 {
+            (self.chr_bank0 as usize & !1) | high   /* 8 KB mode ignores the low bit */
+        }
+        else if high == 0 {
+            self.chr_bank0 as usize
+        }
+        else {
+            self.chr_bank1 as usize
+        }
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7fff if self.prg_ram_enabled() => self.cartridge.read_prg_ram(addr),
+            0x8000..=0xffff => Some(self.cartridge.prg_bank(self.prg_bank_index(addr), PRG_BANK_SIZE)[addr as usize & (PRG_BANK_SIZE - 1)]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x6000..=0x7fff if self.prg_ram_enabled() => self.cartridge.write_prg_ram(addr, byte),
+            /* The serial port ignores a write on the cycle after another one, which
+             * is how the dummy write of an RMW instruction reaches it */
+            0x8000..=0xffff if self.written => {},
+            0x8000..=0xffff => {
+                self.written = true;
+                if byte & 0x80 != 0 {
+                    self.shift = 0;
+                    self.shift_count = 0;
+                    self.control |= 0x0c;
+                    return;
+                }
+
+                self.shift |= (byte & 1) << self.shift_count;
+                self.shift_count += 1;
+
+                if self.shift_count == 5 {
+                    self.write_register(addr, self.shift);
+                    self.shift = 0;
+                    self.shift_count = 0;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.cartridge.chr_bank(self.chr_bank_index(addr), CHR_BANK_SIZE)[addr as usize & (CHR_BANK_SIZE - 1)]
+    }
+
+    fn ppu_write(&mut self, addr: u16, byte: u8) {
+        if self.cartridge.has_chr_ram() {
+            let bank = self.chr_bank_index(addr);
+            self.cartridge.chr_bank_mut(bank, CHR_BANK_SIZE)[addr as usize & (CHR_BANK_SIZE - 1)] = byte;
+        }
+    }
+
+    fn cpu_clock(&mut self, _cycles: u32) {
+        self.written = false;
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0x03 {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use super::Mapper;
+use crate::cartridge::{Cartridge, Mirroring};
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+/* Mapper 4: 8 KB PRG banks, 1 KB CHR banks and a scanline counter driven by PPU A12 */
+pub struct Mmc3 {
+    cartridge: Cartridge,
+    bank_select: u8,        /* CPxxxRRR: CHR A12 inversion, PRG mode, target register */
+    banks: [u8; 8],         /* R0-R5 CHR, R6-R7 PRG */
+    mirroring: Mirroring,
+    prg_ram_enabled: bool,
+    prg_ram_write_protect: bool,
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+}
+
+impl Mmc3 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let mirroring = cartridge.header.mirroring;
+        Mmc3 {
+            cartridge,
+            bank_select: 0,
+            banks: [0, 2, 4, 5, 6, 7, 0, 1],
+            mirroring,
+            prg_ram_enabled: true,
+            prg_ram_write_protect: false,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+        }
+    }
+
+    fn prg_bank_index(&self, addr: u16) -> usize {
+        let count = self.cartridge.prg_bank_count(PRG_BANK_SIZE);
+        let second_last = count.saturating_sub(2);
+        let swap = self.bank_select & 0x40 != 0;
+
+        match (addr >> 13) & 0x03 {
+            0 if swap => second_last,
+            0 => self.banks[6] as usize,
+            1 => self.banks[7] as usize,
+            2 if swap => self.banks[6] as usize,
+            2 => second_last,
+            _ => count - 1,
+        }
+    }
+
+    fn chr_bank_index(&self, addr: u16) -> usize {
+        /* A12 inversion swaps the 2 KB half with the 1 KB half */
+        let addr = if self.bank_select & 0x80 != 0 { addr ^ 0x1000 } else { addr };
+        let slot = (addr >> 10) as usize & 0x07;
+
+        match slot {
+            0 | 1 => (self.banks[0] as usize & !1) | slot,
+            2 | 3 => (self.banks[1] as usize & !1) | (slot & 1),
+            _ => self.banks[slot - 2] as usize,
+        }
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7fff if self.prg_ram_enabled => self.cartridge.read_prg_ram(addr),
+            0x8000..=0xffff => Some(self.cartridge.prg_bank(self.prg_bank_index(addr), PRG_BANK_SIZE)[addr as usize & (PRG_BANK_SIZE - 1)]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, byte: u8) {
+        let even = addr & 1 == 0;
+        match addr {
+            0x6000..=0x7fff if self.prg_ram_enabled && !self.prg_ram_write_protect => {
+                self.cartridge.write_prg_ram(addr, byte);
+            },
+            0x8000..=0x9fff if even => self.bank_select = byte,
+            0x8000..=0x9fff => {
+                let reg = (self.bank_select & 0x07) as usize;
+                /* PRG registers only have 6 bits, R0/R1 ignore the low bit when used */
+                self.banks[reg] = if reg >= 6 { byte & 0x3f } else { byte };
+            },
+            0xa000..=0xbfff if !even => {
+                self.prg_ram_enabled = byte & 0x80 != 0;
+                self.prg_ram_write_protect = byte & 0x40 != 0;
+            },
+            /* Four-screen boards hardwire their nametables */
+            0xa000..=0xbfff if self.cartridge.header.mirroring != Mirroring::FourScreen => {
+                self.mirroring = if byte & 1 == 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+            },
+            0xc000..=0xdfff if even => self.irq_latch = byte,
+            0xc000..=0xdfff => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            },
+            0xe000..=0xffff if even => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            },
+            0xe000..=0xffff => self.irq_enabled = true,
+            _ => {},
+        }
+    }
+
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.cartridge.chr_bank(self.chr_bank_index(addr), CHR_BANK_SIZE)[addr as usize & (CHR_BANK_SIZE - 1)]
+    }
+
+    fn ppu_write(&mut self, addr: u16, byte: u8) {
+        if self.cartridge.has_chr_ram() {
+            let bank = self.chr_bank_index(addr);
+            self.cartridge.chr_bank_mut(bank, CHR_BANK_SIZE)[addr as usize & (CHR_BANK_SIZE - 1)] = byte;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn notify_scanline(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        }
+        else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+}
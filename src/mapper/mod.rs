@@ -0,0 +1,70 @@
+mod nrom;
+mod mmc1;
+mod uxrom;
+mod cnrom;
+mod mmc3;
+mod axrom;
+
+pub use nrom::Nrom;
+pub use mmc1::Mmc1;
+pub use uxrom::Uxrom;
+pub use cnrom::Cnrom;
+pub use mmc3::Mmc3;
+pub use axrom::Axrom;
+
+use crate::cartridge::{
+    Cartridge,
+    CartridgeError,
+    Mirroring,
+};
+
+/* A cartridge board as seen from both buses. CPU addresses are $4020-$FFFF,
+ * PPU addresses are the pattern table range $0000-$1FFF. */
+pub trait Mapper {
+    fn cpu_read(&self, addr: u16) -> Option<u8>;    /* None leaves the data bus floating */
+    fn cpu_write(&mut self, addr: u16, byte: u8);
+    fn ppu_read(&self, addr: u16) -> u8;
+    fn ppu_write(&mut self, addr: u16, byte: u8);
+    fn mirroring(&self) -> Mirroring;
+
+    /* Level of the cartridge's /IRQ output, true when asserted */
+    fn irq(&self) -> bool {
+        false
+    }
+
+    /* Called after every instruction with the CPU cycles it took */
+    fn cpu_clock(&mut self, _cycles: u32) {}
+
+    /* Called by the PPU once per rendered scanline, standing in for boards that watch PPU A12 */
+    fn notify_scanline(&mut self) {}
+}
+
+pub fn from_cartridge(cartridge: Cartridge) -> Result<Box<dyn Mapper>, CartridgeError> {
+    match cartridge.header.mapper {
+        0 => Ok(Box::new(Nrom::new(cartridge))),
+        1 => Ok(Box::new(Mmc1::new(cartridge))),
+        2 => Ok(Box::new(Uxrom::new(cartridge))),
+        3 => Ok(Box::new(Cnrom::new(cartridge))),
+        4 => Ok(Box::new(Mmc3::new(cartridge))),
+        7 => Ok(Box::new(Axrom::new(cartridge))),
+        mapper => Err(CartridgeError::UnsupportedMapper(mapper)),
+    }
+}
+
+/* Discrete-logic boards without bus-conflict protection see the ROM's own byte
+ * fighting the written value; NES 2.0 submapper 2 marks the boards that do. */
+#[inline(always)]
+pub(crate) fn has_bus_conflicts(cartridge: &Cartridge) -> bool {
+    cartridge.header.submapper == 2
+}
+
+/* Shared CHR handling for boards that map a single 8 KB CHR window */
+#[inline(always)]
+pub(crate) fn write_chr_ram(cartridge: &mut Cartridge, bank: usize, addr: u16, byte: u8) {
+    if cartridge.has_chr_ram() {
+        cartridge.chr_bank_mut(bank, 0x2000)[addr as usize & 0x1fff] = byte;
+    }
+}
+
+#[cfg(test)]
+mod tests;
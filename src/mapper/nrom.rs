@@ -0,0 +1,41 @@
+use super::{Mapper, write_chr_ram};
+use crate::cartridge::{Cartridge, Mirroring};
+
+/* Mapper 0: 16 or 32 KB of fixed PRG ROM and 8 KB of fixed CHR */
+pub struct Nrom {
+    cartridge: Cartridge,
+}
+
+impl Nrom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        Nrom { cartridge }
+    }
+}
+
+impl Mapper for Nrom {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7fff => self.cartridge.read_prg_ram(addr),
+            0x8000..=0xffff => Some(self.cartridge.prg_rom[(addr as usize - 0x8000) % self.cartridge.prg_rom.len()]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, byte: u8) {
+        if let 0x6000..=0x7fff = addr {
+            self.cartridge.write_prg_ram(addr, byte);
+        }
+    }
+
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.cartridge.chr_bank(0, 0x2000)[addr as usize & 0x1fff]
+    }
+
+    fn ppu_write(&mut self, addr: u16, byte: u8) {
+        write_chr_ram(&mut self.cartridge, 0, addr, byte);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.cartridge.header.mirroring
+    }
+}
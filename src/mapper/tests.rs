@@ -0,0 +1,276 @@
+use super::{Mapper, from_cartridge};
+use crate::{
+    cartridge::{Mirroring, test_image::TestImage},
+    ppu::Ppu,
+};
+
+/* Every 8 KB of PRG ROM is filled with its bank number and every 1 KB of CHR ROM with
+ * its own, so a read tells which bank is mapped. `chr_units` of zero gives CHR RAM. */
+fn board(mapper: u8, prg_units: u8, chr_units: u8, flags6: u8) -> Box<dyn Mapper> {
+    let image = TestImage::new(mapper, prg_units, chr_units)
+        .with_flags6(flags6)
+        .with_prg_fill(|i| (i / 0x2000) as u8)
+        .with_chr_fill(|i| (i / 0x0400) as u8);
+
+    from_cartridge(image.cartridge()).unwrap()
+}
+
+fn prg(mapper: &dyn Mapper, addr: u16) -> u8 {
+    mapper.cpu_read(addr).expect("PRG ROM is mapped")
+}
+
+/* NROM{{{1*/
+#[test]
+fn nrom_mirrors_16k_and_maps_prg_ram() {
+    let mut nrom = board(0, 1, 1, 0x01);
+    assert_eq!(prg(&*nrom, 0x8000), 0);
+    assert_eq!(prg(&*nrom, 0xa000), 1);
+    assert_eq!(prg(&*nrom, 0xe000), 1);
+    assert_eq!(nrom.mirroring(), Mirroring::Vertical);
+
+    nrom.cpu_write(0x6010, 0x5a);
+    assert_eq!(nrom.cpu_read(0x6010), Some(0x5a));
+    assert_eq!(nrom.cpu_read(0x5000), None);
+    assert_eq!(nrom.ppu_read(0x1400), 5);
+}
+
+/* UxROM and CNROM{{{1*/
+#[test]
+fn uxrom_switches_8000_and_fixes_the_last_bank() {
+    let mut uxrom = board(2, 8, 0, 0);
+    uxrom.cpu_write(0x8000, 3);
+    assert_eq!(prg(&*uxrom, 0x8000), 6);
+    assert_eq!(prg(&*uxrom, 0xbfff), 7);
+    assert_eq!(prg(&*uxrom, 0xc000), 14);
+
+    /* Bank numbers past the end wrap */
+    uxrom.cpu_write(0xffff, 9);
+    assert_eq!(prg(&*uxrom, 0x8000), 2);
+
+    uxrom.ppu_write(0x0010, 0x77);
+    assert_eq!(uxrom.ppu_read(0x0010), 0x77);
+}
+
+#[test]
+fn cnrom_switches_chr() {
+    let mut cnrom = board(3, 2, 4, 0);
+    assert_eq!(cnrom.ppu_read(0x0000), 0);
+
+    cnrom.cpu_write(0x8000, 2);
+    assert_eq!(cnrom.ppu_read(0x0000), 16);
+    assert_eq!(cnrom.ppu_read(0x1c00), 23);
+
+    /* CHR ROM ignores writes */
+    cnrom.ppu_write(0x0000, 0xff);
+    assert_eq!(cnrom.ppu_read(0x0000), 16);
+}
+
+/* AxROM{{{1*/
+#[test]
+fn axrom_switches_32k_and_picks_the_screen() {
+    let mut axrom = board(7, 8, 0, 0);
+    assert_eq!(axrom.mirroring(), Mirroring::SingleScreenLower);
+
+    axrom.cpu_write(0x8000, 0x12);
+    assert_eq!(prg(&*axrom, 0x8000), 8);
+    assert_eq!(prg(&*axrom, 0xe000), 11);
+    assert_eq!(axrom.mirroring(), Mirroring::SingleScreenUpper);
+
+    axrom.cpu_write(0x8000, 0x01);
+    assert_eq!(prg(&*axrom, 0x8000), 4);
+    assert_eq!(axrom.mirroring(), Mirroring::SingleScreenLower);
+}
+
+/* MMC1{{{1*/
+/* Loads a register through the serial port, one instruction per bit */
+fn mmc1_write(mmc1: &mut dyn Mapper, addr: u16, value: u8) {
+    for bit in 0..5 {
+        mmc1.cpu_write(addr, value >> bit);
+        mmc1.cpu_clock(2);
+    }
+}
+
+#[test]
+fn mmc1_powers_on_with_the_last_bank_fixed() {
+    let mmc1 = board(1, 8, 2, 0);
+    assert_eq!(prg(&*mmc1, 0x8000), 0);
+    assert_eq!(prg(&*mmc1, 0xc000), 14);
+}
+
+#[test]
+fn mmc1_prg_modes() {
+    let mut mmc1 = board(1, 8, 2, 0);
+    mmc1_write(&mut *mmc1, 0xe000, 5);
+    assert_eq!(prg(&*mmc1, 0x8000), 10);
+    assert_eq!(prg(&*mmc1, 0xc000), 14);
+
+    mmc1_write(&mut *mmc1, 0x8000, 0x08);     /* First bank fixed at $8000 */
+    assert_eq!(prg(&*mmc1, 0x8000), 0);
+    assert_eq!(prg(&*mmc1, 0xc000), 10);
+
+    mmc1_write(&mut *mmc1, 0x8000, 0x00);     /* 32 KB, low bit of the bank ignored */
+    assert_eq!(prg(&*mmc1, 0x8000), 8);
+    assert_eq!(prg(&*mmc1, 0xc000), 10);
+}
+
+#[test]
+fn mmc1_chr_modes_and_mirroring() {
+    let mut mmc1 = board(1, 2, 4, 0);
+    mmc1_write(&mut *mmc1, 0xa000, 3);
+    mmc1_write(&mut *mmc1, 0xc000, 5);
+    assert_eq!(mmc1.ppu_read(0x0000), 8);     /* 8 KB mode: banks 2 and 3 */
+    assert_eq!(mmc1.ppu_read(0x1000), 12);
+
+    mmc1_write(&mut *mmc1, 0x8000, 0x1f);     /* 4 KB mode, horizontal */
+    assert_eq!(mmc1.ppu_read(0x0000), 12);
+    assert_eq!(mmc1.ppu_read(0x1000), 20);
+    assert_eq!(mmc1.mirroring(), Mirroring::Horizontal);
+
+    mmc1_write(&mut *mmc1, 0x8000, 0x11);
+    assert_eq!(mmc1.mirroring(), Mirroring::SingleScreenUpper);
+    mmc1_write(&mut *mmc1, 0x8000, 0x10);
+    assert_eq!(mmc1.mirroring(), Mirroring::SingleScreenLower);
+}
+
+#[test]
+fn mmc1_reset_clears_the_shift_register() {
+    let mut mmc1 = board(1, 8, 2, 0);
+    mmc1_write(&mut *mmc1, 0x8000, 0x08);
+
+    /* Three bits in, then a reset: the partial value is dropped and PRG mode 3 comes back */
+    for _ in 0..3 {
+        mmc1.cpu_write(0xe000, 1);
+        mmc1.cpu_clock(2);
+    }
+    mmc1.cpu_write(0x8000, 0x80);
+    mmc1.cpu_clock(2);
+    assert_eq!(prg(&*mmc1, 0xc000), 14);
+
+    mmc1_write(&mut *mmc1, 0xe000, 2);
+    assert_eq!(prg(&*mmc1, 0x8000), 4);
+}
+
+#[test]
+fn mmc1_ignores_the_second_write_of_an_instruction() {
+    let mut mmc1 = board(1, 8, 2, 0);
+
+    /* A read-modify-write's dummy write and final write land on consecutive cycles */
+    for bit in 0..5 {
+        mmc1.cpu_write(0xe000, 3 >> bit);
+        mmc1.cpu_write(0xe000, 0);
+        mmc1.cpu_clock(6);
+    }
+    assert_eq!(prg(&*mmc1, 0x8000), 6);
+}
+
+#[test]
+fn mmc1_prg_ram_disable() {
+    let mut mmc1 = board(1, 2, 1, 0);
+    mmc1.cpu_write(0x6000, 0x99);
+    assert_eq!(mmc1.cpu_read(0x6000), Some(0x99));
+
+    mmc1_write(&mut *mmc1, 0xe000, 0x10);
+    assert_eq!(mmc1.cpu_read(0x6000), None);
+}
+
+/* MMC3{{{1*/
+#[test]
+fn mmc3_prg_modes() {
+    let mut mmc3 = board(4, 4, 8, 0);     /* Eight 8 KB PRG banks */
+    mmc3.cpu_write(0x8000, 6);
+    mmc3.cpu_write(0x8001, 2);
+    mmc3.cpu_write(0x8000, 7);
+    mmc3.cpu_write(0x8001, 3);
+    assert_eq!(prg(&*mmc3, 0x8000), 2);
+    assert_eq!(prg(&*mmc3, 0xa000), 3);
+    assert_eq!(prg(&*mmc3, 0xc000), 6);
+    assert_eq!(prg(&*mmc3, 0xe000), 7);
+
+    mmc3.cpu_write(0x8000, 0x40);           /* Swap $8000 and $C000 */
+    assert_eq!(prg(&*mmc3, 0x8000), 6);
+    assert_eq!(prg(&*mmc3, 0xa000), 3);
+    assert_eq!(prg(&*mmc3, 0xc000), 2);
+    assert_eq!(prg(&*mmc3, 0xe000), 7);
+}
+
+#[test]
+fn mmc3_chr_modes() {
+    let mut mmc3 = board(4, 2, 8, 0);     /* 64 1 KB CHR banks */
+    for (reg, bank) in [(0, 9), (1, 20), (2, 30), (3, 31), (4, 32), (5, 33)] {
+        mmc3.cpu_write(0x8000, reg);
+        mmc3.cpu_write(0x8001, bank);
+    }
+
+    /* 2 KB banks ignore their low bit */
+    let slots = |mmc3: &dyn Mapper| (0..8).map(|slot| mmc3.ppu_read(slot * 0x400)).collect::<Vec<_>>();
+    assert_eq!(slots(&*mmc3), [8, 9, 20, 21, 30, 31, 32, 33]);
+
+    mmc3.cpu_write(0x8000, 0x80);           /* A12 inversion */
+    assert_eq!(slots(&*mmc3), [30, 31, 32, 33, 8, 9, 20, 21]);
+}
+
+#[test]
+fn mmc3_mirroring_and_prg_ram_protect() {
+    let mut mmc3 = board(4, 2, 1, 0);
+    mmc3.cpu_write(0xa000, 1);
+    assert_eq!(mmc3.mirroring(), Mirroring::Horizontal);
+    mmc3.cpu_write(0xa000, 0);
+    assert_eq!(mmc3.mirroring(), Mirroring::Vertical);
+
+    mmc3.cpu_write(0x6000, 0x11);
+    mmc3.cpu_write(0xa001, 0xc0);           /* Enabled, write-protected */
+    mmc3.cpu_write(0x6000, 0x22);
+    assert_eq!(mmc3.cpu_read(0x6000), Some(0x11));
+
+    mmc3.cpu_write(0xa001, 0x00);
+    assert_eq!(mmc3.cpu_read(0x6000), None);
+}
+
+#[test]
+fn mmc3_irq_counts_down_from_the_latch() {
+    let mut mmc3 = board(4, 2, 1, 0);
+    mmc3.cpu_write(0xc000, 3);
+    mmc3.cpu_write(0xc001, 0);              /* Reload on the next clock */
+    mmc3.cpu_write(0xe001, 0);
+
+    for _ in 0..3 {
+        mmc3.notify_scanline();
+        assert!(!mmc3.irq());
+    }
+    mmc3.notify_scanline();
+    assert!(mmc3.irq());
+
+    /* Acknowledging through $E000 also disables the interrupt until $E001 */
+    mmc3.cpu_write(0xe000, 0);
+    assert!(!mmc3.irq());
+    for _ in 0..4 {
+        mmc3.notify_scanline();
+    }
+    assert!(!mmc3.irq());
+
+    mmc3.cpu_write(0xe001, 0);
+    for _ in 0..4 {
+        mmc3.notify_scanline();
+    }
+    assert!(mmc3.irq());
+}
+
+#[test]
+fn mmc3_irq_fires_on_the_latched_scanline() {
+    let mut mapper = Some(board(4, 2, 1, 0));
+    if let Some(mmc3) = &mut mapper {
+        mmc3.cpu_write(0xc000, 20);
+        mmc3.cpu_write(0xc001, 0);
+        mmc3.cpu_write(0xe001, 0);
+    }
+
+    /* The counter is clocked once per rendered line, when A12 rises for the sprite fetches */
+    let mut ppu = Ppu::new();
+    ppu.mask = 0x18;
+    while !mapper.as_ref().unwrap().irq() {
+        ppu.step(&mut mapper);
+        assert!(ppu.scanline < 240, "MMC3 IRQ never fired");
+    }
+    assert_eq!((ppu.scanline, ppu.dot), (20, 261));
+}
+//}}}1
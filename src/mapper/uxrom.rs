@@ -0,0 +1,56 @@
+use super::{Mapper, has_bus_conflicts, write_chr_ram};
+use crate::cartridge::{Cartridge, Mirroring};
+
+const PRG_BANK_SIZE: usize = 0x4000;
+
+/* Mapper 2: switchable 16 KB at $8000, last 16 KB fixed at $C000, CHR RAM */
+pub struct Uxrom {
+    cartridge: Cartridge,
+    prg_bank: usize,
+    bus_conflicts: bool,
+}
+
+impl Uxrom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let bus_conflicts = has_bus_conflicts(&cartridge);
+        Uxrom { cartridge, prg_bank: 0, bus_conflicts }
+    }
+}
+
+impl Mapper for Uxrom {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        let offset = addr as usize & (PRG_BANK_SIZE - 1);
+        match addr {
+            0x6000..=0x7fff => self.cartridge.read_prg_ram(addr),
+            0x8000..=0xbfff => Some(self.cartridge.prg_bank(self.prg_bank, PRG_BANK_SIZE)[offset]),
+            0xc000..=0xffff => {
+                let last = self.cartridge.prg_bank_count(PRG_BANK_SIZE) - 1;
+                Some(self.cartridge.prg_bank(last, PRG_BANK_SIZE)[offset])
+            },
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x6000..=0x7fff => self.cartridge.write_prg_ram(addr, byte),
+            0x8000..=0xffff => {
+                let byte = if self.bus_conflicts { byte & self.cpu_read(addr).unwrap_or(0xff) } else { byte };
+                self.prg_bank = byte as usize;
+            },
+            _ => {},
+        }
+    }
+
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.cartridge.chr_bank(0, 0x2000)[addr as usize & 0x1fff]
+    }
+
+    fn ppu_write(&mut self, addr: u16, byte: u8) {
+        write_chr_ram(&mut self.cartridge, 0, addr, byte);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.cartridge.header.mirroring
+    }
+}
@@ -1,8 +1,38 @@
 use super::{Readable,Writable,Endianness};
+use crate::{
+    cartridge::{
+        Cartridge,
+        CartridgeError,
+    },
+    mapper::{self, Mapper},
+    ppu::Ppu,
+    apu::{Apu, DMC_STALL_CYCLES},
+    controller::{Joypad, CONTROLLER_PORTS},
+    region::Region,
+};
 
+pub const ADDRESS_SPACE_LEN: u32 = 0x10000;
+pub const RAM_SIZE: usize = 0x800;
+pub const OAM_DMA: u16 = 0x14;              /* $4014 */
+pub const APU_STATUS: u16 = 0x15;           /* $4015 */
+pub const JOYPAD_1: u16 = 0x16;             /* $4016, also the strobe for both ports */
+pub const JOYPAD_2: u16 = 0x17;             /* $4017 when read, the APU frame counter when written */
+
+/* CPU address map:
+ *  $0000-$07FF  2 KB internal RAM, mirrored through $1FFF
+ *  $2000-$2007  PPU registers, mirrored every 8 bytes through $3FFF
+ *  $4000-$401F  APU and I/O registers
+ *  $4020-$FFFF  Cartridge space, decoded by the board's mapper */
 pub struct Bus {
-    mem: Vec<u8>,
-    pub len: u32,
+    ram: [u8; RAM_SIZE],
+    pub ppu: Ppu,
+    pub apu: Apu,
+    pub controllers: [Joypad; CONTROLLER_PORTS],
+    pub mapper: Option<Box<dyn Mapper>>,
+    pub oam_dma: Option<u8>,    /* Page written to $4014, waiting for the CPU to halt */
+    pub dmc_stall: u32,         /* CPU cycles owed to DMC sample fetches */
+    region: Region,
+    dot_fraction: u32,          /* PPU dots owed, in units of the region's dot ratio denominator */
 }
 
 impl Default for Bus {
@@ -14,40 +44,174 @@ impl Default for Bus {
 impl Bus {
     pub fn new() -> Self {
         Bus {
-            mem: vec![0; 0x1f400],
-            len: 0x1f400,
+            ram: [0; RAM_SIZE],
+            ppu: Ppu::new(),
+            apu: Apu::new(),
+            controllers: Default::default(),
+            mapper: None,
+            oam_dma: None,
+            dmc_stall: 0,
+            region: Region::default(),
+            dot_fraction: 0,
+        }
+    }
+
+    /* The console's 2 KB of internal RAM, without mirrors */
+    pub fn ram(&self) -> &[u8; RAM_SIZE] {
+        &self.ram
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.ppu.region = region;
+        self.apu.set_region(region);
+        self.dot_fraction = 0;
+    }
+
+    /* Advances the devices on the bus by `cycles` CPU cycles. The PPU runs three dots per
+     * cycle, or sixteen every five cycles on PAL. */
+    pub fn tick(&mut self, cycles: u32) {
+        let (dots, per_cycles) = self.region.dot_ratio();
+
+        for _ in 0..cycles {
+            self.dot_fraction += dots;
+            while self.dot_fraction >= per_cycles {
+                self.ppu.step(&mut self.mapper);
+                self.dot_fraction -= per_cycles;
+            }
+
+            self.apu.step();
+            if let Some(addr) = self.apu.dmc.fetch_address() {
+                let byte = self.read_byte(addr);
+                self.apu.dmc.fill(byte);
+                self.dmc_stall += DMC_STALL_CYCLES;
+            }
+        }
+
+        if let Some(mapper) = &mut self.mapper {
+            mapper.cpu_clock(cycles);
+        }
+    }
+
+    /* Copies $XX00-$XXFF to OAM through $2004, so the copy starts at OAMADDR */
+    pub fn run_oam_dma(&mut self, page: u8) {
+        let base = (page as u16) << 8;
+        for offset in 0..=0xff {
+            let byte = self.read_byte(base | offset);
+            self.write_ppu_register(4, byte);
+        }
+    }
+
+    /* The console takes on the region the header asks for; set_region() overrides it */
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge) -> Result<(), CartridgeError> {
+        let region = cartridge.header.region;
+        self.mapper = Some(mapper::from_cartridge(cartridge)?);
+        self.set_region(region);
+        Ok(())
+    }
+
+    fn read_ppu_register(&mut self, reg: u16) -> u8 {
+        self.ppu.read_register(&self.mapper, reg)
+    }
+
+    fn peek_ppu_register(&self, reg: u16) -> u8 {
+        self.ppu.peek_register(reg)
+    }
+
+    fn write_ppu_register(&mut self, reg: u16, byte: u8) {
+        self.ppu.write_register(&mut self.mapper, reg, byte);
+    }
+
+    /* The pads only drive D0; D5-D7 are left floating */
+    #[inline(always)]
+    fn joypad_bits(reg: u16, bit: u8) -> u8 {
+        (Bus::open_bus(0x4000 | reg) & 0xe0) | bit
+    }
+
+    fn read_io_register(&mut self, reg: u16) -> u8 {
+        match reg {
+            APU_STATUS => self.apu.read_status(),
+            JOYPAD_1 => Bus::joypad_bits(reg, self.controllers[0].read()),
+            JOYPAD_2 => Bus::joypad_bits(reg, self.controllers[1].read()),
+            _ => Bus::open_bus(0x4000 | reg),   /* Write-only */
+        }
+    }
+
+    fn peek_io_register(&self, reg: u16) -> u8 {
+        match reg {
+            APU_STATUS => self.apu.peek_status(),
+            JOYPAD_1 => Bus::joypad_bits(reg, self.controllers[0].peek()),
+            JOYPAD_2 => Bus::joypad_bits(reg, self.controllers[1].peek()),
+            _ => Bus::open_bus(0x4000 | reg),
         }
     }
 
-    pub fn as_mut(&mut self, addr: u32) -> &mut u8 {
-        &mut self.mem[addr as usize]
+    fn write_io_register(&mut self, reg: u16, byte: u8) {
+        match reg {
+            OAM_DMA => self.oam_dma = Some(byte),
+            JOYPAD_1 => self.controllers.iter_mut().for_each(|pad| pad.write(byte)),
+            0x00..=0x13 | APU_STATUS | JOYPAD_2 => self.apu.write_register(reg, byte),
+            _ => {},
+        }
+    }
+
+    fn read_cartridge(&self, addr: u16) -> Option<u8> {
+        self.mapper.as_ref()?.cpu_read(addr)
+    }
+
+    fn write_cartridge(&mut self, addr: u16, byte: u8) {
+        if let Some(mapper) = &mut self.mapper {
+            mapper.cpu_write(addr, byte);
+        }
     }
 
-    pub fn as_slice(&self, start: u32) -> &[u8] {
-        &self.mem[(start as usize)..]
+    /* Nothing drives the data bus on unmapped reads, so the last byte fetched lingers.
+     * That is almost always the high byte of the operand, i.e. of the address itself. */
+    #[inline(always)]
+    fn open_bus(addr: u16) -> u8 {
+        (addr >> 8) as u8
     }
 }
 
-impl Readable<u32> for Bus {
+impl Readable<u16> for Bus {
     fn has_endian(&self) -> Endianness {
         Endianness::Little
     }
 
-    fn read_byte(&self, addr: u32) -> u8 {
-        self.mem[(addr as usize) % self.len as usize]
+    fn read_byte(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x1fff => self.ram[addr as usize & (RAM_SIZE - 1)],
+            0x2000..=0x3fff => self.read_ppu_register(addr & 0x7),
+            0x4000..=0x401f => self.read_io_register(addr & 0x1f),
+            0x4020..=0xffff => self.read_cartridge(addr).unwrap_or(Bus::open_bus(addr)),
+        }
     }
-}
 
-impl Writable<u32> for Bus {
-    fn as_mut_slice(&mut self, addr: u32) -> &mut [u8] {
-        &mut self.mem[(addr as usize)..]
+    fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x1fff => self.ram[addr as usize & (RAM_SIZE - 1)],
+            0x2000..=0x3fff => self.peek_ppu_register(addr & 0x7),
+            0x4000..=0x401f => self.peek_io_register(addr & 0x1f),
+            0x4020..=0xffff => self.read_cartridge(addr).unwrap_or(Bus::open_bus(addr)),
+        }
     }
+}
 
+impl Writable<u16> for Bus {
     fn has_endian(&self) -> Endianness {
         Endianness::Little
     }
 
-    fn write_byte(&mut self, addr: u32, byte: u8) {
-        self.mem[(addr as usize) % self.len as usize] = byte;
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1fff => self.ram[addr as usize & (RAM_SIZE - 1)] = byte,
+            0x2000..=0x3fff => self.write_ppu_register(addr & 0x7, byte),
+            0x4000..=0x401f => self.write_io_register(addr & 0x1f, byte),
+            0x4020..=0xffff => self.write_cartridge(addr, byte),
+        }
     }
 }
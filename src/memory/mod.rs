@@ -1,10 +1,22 @@
 mod writable;
 mod bus;
 
-pub use bus::Bus;
+pub use bus::{
+    Bus,
+    ADDRESS_SPACE_LEN,
+    RAM_SIZE,
+    OAM_DMA,
+    APU_STATUS,
+    JOYPAD_1,
+    JOYPAD_2,
+};
 
 pub use writable::{
     Writable,
     Readable,
     Endianness,
+    Address,
 };
+
+#[cfg(test)]
+mod tests;
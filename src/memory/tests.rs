@@ -0,0 +1,62 @@
+use super::{Bus, Readable, Writable};
+use crate::{
+    cartridge::test_image::TestImage,
+    region::Region,
+};
+
+#[test]
+fn ram_is_mirrored_through_1fff() {
+    let mut bus = Bus::new();
+    bus.write_byte(0x0123, 0x42);
+    assert_eq!(bus.read_byte(0x0923), 0x42);
+    assert_eq!(bus.read_byte(0x1923), 0x42);
+
+    bus.write_byte(0x1fff, 0x24);
+    assert_eq!(bus.peek(0x07ff), 0x24);
+}
+
+#[test]
+fn words_wrap_at_the_top_of_the_address_space() {
+    let mut bus = Bus::new();
+    bus.write_word(0xffff, 0x1234);     /* Nothing mapped at $FFFF, so only $0000 keeps a byte */
+    assert_eq!(bus.peek(0x0000), 0x12);
+
+    /* Without a cartridge $FFFF reads as open bus, the high byte of the address */
+    assert_eq!(bus.peek_word(0xffff), 0x12ff);
+    assert_eq!(bus.read_word(0xffff), 0x12ff);
+}
+
+#[test]
+fn write_only_registers_read_as_open_bus() {
+    let mut bus = Bus::new();
+    bus.write_byte(0x4000, 0xbf);
+    bus.write_byte(0x4014, 0x02);
+
+    assert_eq!(bus.read_byte(0x4000), 0x40);
+    assert_eq!(bus.peek(0x4013), 0x40);
+    assert_eq!(bus.read_byte(0x4018), 0x40);
+}
+
+#[test]
+fn joypads_drive_only_the_low_bit() {
+    let mut bus = Bus::new();
+    bus.controllers[0].set_buttons(0x01);
+    bus.write_byte(0x4016, 1);
+    bus.write_byte(0x4016, 0);
+
+    assert_eq!(bus.read_byte(0x4016), 0x41);
+    assert_eq!(bus.read_byte(0x4016), 0x40);
+}
+
+#[test]
+fn cartridge_sets_the_region_until_overridden() {
+    let mut bus = Bus::new();
+    bus.insert_cartridge(TestImage::new(0, 1, 1).with_header_byte(9, 0x01).cartridge()).unwrap();  /* iNES PAL bit */
+    assert_eq!(bus.region(), Region::Pal);
+    assert_eq!(bus.ppu.region, Region::Pal);
+    assert_eq!(bus.apu.region(), Region::Pal);
+
+    bus.set_region(Region::Dendy);
+    assert_eq!(bus.ppu.region, Region::Dendy);
+    assert_eq!(bus.apu.region(), Region::Dendy);
+}
@@ -1,32 +1,50 @@
-use std::ops::Add;
-
 pub enum Endianness {
     Big,
     Little,
 }
 
+/* Address types step to the next byte the way the address bus does, wrapping at the top */
+pub trait Address: From<u8> + Copy {
+    fn wrapping_add(self, rhs: Self) -> Self;
+}
+
+impl Address for u16 {
+    fn wrapping_add(self, rhs: u16) -> u16 {
+        u16::wrapping_add(self, rhs)
+    }
+}
+
 pub trait Readable<T>
 where
-    T: Add<T, Output = T> + From<u8> + Copy
+    T: Address
 {
     fn has_endian(&self) -> Endianness;
-    fn read_byte(&self, addr: T) -> u8;
+    fn read_byte(&mut self, addr: T) -> u8;     /* A real bus read; devices may react to it */
+    fn peek(&self, addr: T) -> u8;              /* Inspects memory without side effects */
+
+    fn read_word(&mut self, addr: T) -> u16 {
+        let (shift1, shift2) = match self.has_endian() {
+            Endianness::Big => (8, 0),
+            Endianness::Little => (0, 8),
+        };
+
+        ((self.read_byte(addr) as u16) << shift1) | ((self.read_byte(addr.wrapping_add(T::from(1))) as u16) << shift2)
+    }
 
-    fn read_word(&self, addr: T) -> u16 {
+    fn peek_word(&self, addr: T) -> u16 {
         let (shift1, shift2) = match self.has_endian() {
             Endianness::Big => (8, 0),
             Endianness::Little => (0, 8),
         };
 
-        ((self.read_byte(addr) as u16) << shift1) | ((self.read_byte(addr + T::from(1)) as u16) << shift2)
+        ((self.peek(addr) as u16) << shift1) | ((self.peek(addr.wrapping_add(T::from(1))) as u16) << shift2)
     }
 }
 
 pub trait Writable<T>
 where
-    T: Add<T, Output = T> + From<u8> + Copy
+    T: Address
 {
-    fn as_mut_slice(&mut self, addr: T) -> &mut [u8];
     fn has_endian(&self) -> Endianness;
     fn write_byte(&mut self, addr: T, byte: u8);
 
@@ -37,6 +55,6 @@ where
         };
 
         self.write_byte(addr, (word >> shift1) as u8);
-        self.write_byte(addr + T::from(1), (word >> shift2) as u8);
+        self.write_byte(addr.wrapping_add(T::from(1)), (word >> shift2) as u8);
     }
 }
@@ -17,6 +17,18 @@ pub enum MemAddressMode {
     ZeroPageIndexedY,   /* Operand is address in the zero page, incremented by y register w/o carry */
 }
 
+impl MemAddressMode {
+    /* Number of operand bytes following the opcode */
+    pub fn operand_len(&self) -> u16 {
+        match self {
+            MemAddressMode::Accumulator | MemAddressMode::Implied => 0,
+            MemAddressMode::Absolute | MemAddressMode::AbsoluteIndexedX
+                | MemAddressMode::AbsoluteIndexedY | MemAddressMode::Indirect => 2,
+            _ => 1,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum Opcode {
     ADC,    /* Add with carry */
@@ -75,255 +87,351 @@ pub enum Opcode {
     TXA,    /* Transfer x to accumulator */
     TXS,    /* Transfer x to stack pointer */
     TYA,    /* Transfer y to accumulator */
+
+    /* Unofficial opcodes */
+    ALR,    /* And with accumulator, then logical shift right */
+    ANC,    /* And with accumulator, copy bit 7 into carry */
+    ANE,    /* Unstable: (accumulator | magic) & x & operand into accumulator */
+    ARR,    /* And with accumulator, then rotate right with odd carry/overflow */
+    AXS,    /* (Accumulator & x) - operand into x without borrow */
+    DCP,    /* Decrement, then compare with accumulator */
+    ISC,    /* Increment, then subtract with carry */
+    JAM,    /* Halts the processor until reset */
+    LAS,    /* Memory & stack pointer into accumulator, x and stack pointer */
+    LAX,    /* Load accumulator and x */
+    LXA,    /* Unstable: (accumulator | magic) & operand into accumulator and x */
+    RLA,    /* Rotate left, then and with accumulator */
+    RRA,    /* Rotate right, then add with carry */
+    SAX,    /* Store accumulator & x */
+    SHA,    /* Unstable: store accumulator & x & (high address byte + 1) */
+    SHX,    /* Unstable: store x & (high address byte + 1) */
+    SHY,    /* Unstable: store y & (high address byte + 1) */
+    SLO,    /* Arithmetic shift left, then or with accumulator */
+    SRE,    /* Logical shift right, then xor with accumulator */
+    TAS,    /* Unstable: accumulator & x into stack pointer, then store like SHA */
 }
 
 pub static OPCODES: [Option<(Opcode, MemAddressMode)>; 256] = [
     //0x00
     Some((Opcode::BRK, MemAddressMode::Implied)),
     Some((Opcode::ORA, MemAddressMode::IndirectIndexedX)),
-    None, None, None,
+    Some((Opcode::JAM, MemAddressMode::Implied)),
+    Some((Opcode::SLO, MemAddressMode::IndirectIndexedX)),
+    Some((Opcode::NOP, MemAddressMode::ZeroPage)),
     Some((Opcode::ORA, MemAddressMode::ZeroPage)),
     Some((Opcode::ASL, MemAddressMode::ZeroPage)),
-    None,
+    Some((Opcode::SLO, MemAddressMode::ZeroPage)),
     Some((Opcode::PHP, MemAddressMode::Implied)),
     Some((Opcode::ORA, MemAddressMode::Immediate)),
     Some((Opcode::ASL, MemAddressMode::Accumulator)),
-    None, None,
+    Some((Opcode::ANC, MemAddressMode::Immediate)),
+    Some((Opcode::NOP, MemAddressMode::Absolute)),
     Some((Opcode::ORA, MemAddressMode::Absolute)),
     Some((Opcode::ASL, MemAddressMode::Absolute)),
-    None,
+    Some((Opcode::SLO, MemAddressMode::Absolute)),
 
     //0x10
     Some((Opcode::BPL, MemAddressMode::Relative)),
     Some((Opcode::ORA, MemAddressMode::IndirectIndexedY)),
-    None, None, None,
+    Some((Opcode::JAM, MemAddressMode::Implied)),
+    Some((Opcode::SLO, MemAddressMode::IndirectIndexedY)),
+    Some((Opcode::NOP, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::ORA, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::ASL, MemAddressMode::ZeroPageIndexedX)),
-    None,
+    Some((Opcode::SLO, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::CLC, MemAddressMode::Implied)),
     Some((Opcode::ORA, MemAddressMode::AbsoluteIndexedY)),
-    None, None, None,
+    Some((Opcode::NOP, MemAddressMode::Implied)),
+    Some((Opcode::SLO, MemAddressMode::AbsoluteIndexedY)),
+    Some((Opcode::NOP, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::ORA, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::ASL, MemAddressMode::AbsoluteIndexedX)),
-    None,
+    Some((Opcode::SLO, MemAddressMode::AbsoluteIndexedX)),
 
     //0x20
     Some((Opcode::JSR, MemAddressMode::Absolute)),
     Some((Opcode::AND, MemAddressMode::IndirectIndexedX)),
-    None, None,
+    Some((Opcode::JAM, MemAddressMode::Implied)),
+    Some((Opcode::RLA, MemAddressMode::IndirectIndexedX)),
     Some((Opcode::BIT, MemAddressMode::ZeroPage)),
     Some((Opcode::AND, MemAddressMode::ZeroPage)),
     Some((Opcode::ROL, MemAddressMode::ZeroPage)),
-    None,
+    Some((Opcode::RLA, MemAddressMode::ZeroPage)),
     Some((Opcode::PLP, MemAddressMode::Implied)),
     Some((Opcode::AND, MemAddressMode::Immediate)),
     Some((Opcode::ROL, MemAddressMode::Accumulator)),
-    None,
+    Some((Opcode::ANC, MemAddressMode::Immediate)),
     Some((Opcode::BIT, MemAddressMode::Absolute)),
     Some((Opcode::AND, MemAddressMode::Absolute)),
     Some((Opcode::ROL, MemAddressMode::Absolute)),
-    None,
+    Some((Opcode::RLA, MemAddressMode::Absolute)),
 
     //0x30
     Some((Opcode::BMI, MemAddressMode::Relative)),
     Some((Opcode::AND, MemAddressMode::IndirectIndexedY)),
-    None, None, None,
+    Some((Opcode::JAM, MemAddressMode::Implied)),
+    Some((Opcode::RLA, MemAddressMode::IndirectIndexedY)),
+    Some((Opcode::NOP, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::AND, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::ROL, MemAddressMode::ZeroPageIndexedX)),
-    None,
+    Some((Opcode::RLA, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::SEC, MemAddressMode::Implied)),
     Some((Opcode::AND, MemAddressMode::AbsoluteIndexedY)),
-    None, None, None,
+    Some((Opcode::NOP, MemAddressMode::Implied)),
+    Some((Opcode::RLA, MemAddressMode::AbsoluteIndexedY)),
+    Some((Opcode::NOP, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::AND, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::ROL, MemAddressMode::AbsoluteIndexedX)),
-    None,
+    Some((Opcode::RLA, MemAddressMode::AbsoluteIndexedX)),
 
     //0x40
     Some((Opcode::RTI, MemAddressMode::Implied)),
     Some((Opcode::EOR, MemAddressMode::IndirectIndexedX)),
-    None, None, None,
+    Some((Opcode::JAM, MemAddressMode::Implied)),
+    Some((Opcode::SRE, MemAddressMode::IndirectIndexedX)),
+    Some((Opcode::NOP, MemAddressMode::ZeroPage)),
     Some((Opcode::EOR, MemAddressMode::ZeroPage)),
     Some((Opcode::LSR, MemAddressMode::ZeroPage)),
-    None,
+    Some((Opcode::SRE, MemAddressMode::ZeroPage)),
     Some((Opcode::PHA, MemAddressMode::Implied)),
     Some((Opcode::EOR, MemAddressMode::Immediate)),
     Some((Opcode::LSR, MemAddressMode::Accumulator)),
-    None,
+    Some((Opcode::ALR, MemAddressMode::Immediate)),
     Some((Opcode::JMP, MemAddressMode::Absolute)),
     Some((Opcode::EOR, MemAddressMode::Absolute)),
     Some((Opcode::LSR, MemAddressMode::Absolute)),
-    None,
+    Some((Opcode::SRE, MemAddressMode::Absolute)),
 
     //0x50
     Some((Opcode::BVC, MemAddressMode::Relative)),
     Some((Opcode::EOR, MemAddressMode::IndirectIndexedY)),
-    None, None, None,
+    Some((Opcode::JAM, MemAddressMode::Implied)),
+    Some((Opcode::SRE, MemAddressMode::IndirectIndexedY)),
+    Some((Opcode::NOP, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::EOR, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::LSR, MemAddressMode::ZeroPageIndexedX)),
-    None,
+    Some((Opcode::SRE, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::CLI, MemAddressMode::Implied)),
     Some((Opcode::EOR, MemAddressMode::AbsoluteIndexedY)),
-    None, None, None,
+    Some((Opcode::NOP, MemAddressMode::Implied)),
+    Some((Opcode::SRE, MemAddressMode::AbsoluteIndexedY)),
+    Some((Opcode::NOP, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::EOR, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::LSR, MemAddressMode::AbsoluteIndexedX)),
-    None,
+    Some((Opcode::SRE, MemAddressMode::AbsoluteIndexedX)),
 
     //0x60
     Some((Opcode::RTS, MemAddressMode::Implied)),
     Some((Opcode::ADC, MemAddressMode::IndirectIndexedX)),
-    None, None, None,
+    Some((Opcode::JAM, MemAddressMode::Implied)),
+    Some((Opcode::RRA, MemAddressMode::IndirectIndexedX)),
+    Some((Opcode::NOP, MemAddressMode::ZeroPage)),
     Some((Opcode::ADC, MemAddressMode::ZeroPage)),
     Some((Opcode::ROR, MemAddressMode::ZeroPage)),
-    None,
+    Some((Opcode::RRA, MemAddressMode::ZeroPage)),
     Some((Opcode::PLA, MemAddressMode::Implied)),
     Some((Opcode::ADC, MemAddressMode::Immediate)),
     Some((Opcode::ROR, MemAddressMode::Accumulator)),
-    None,
+    Some((Opcode::ARR, MemAddressMode::Immediate)),
     Some((Opcode::JMP, MemAddressMode::Indirect)),
     Some((Opcode::ADC, MemAddressMode::Absolute)),
     Some((Opcode::ROR, MemAddressMode::Absolute)),
-    None,
+    Some((Opcode::RRA, MemAddressMode::Absolute)),
 
     //0x70
     Some((Opcode::BVS, MemAddressMode::Relative)),
     Some((Opcode::ADC, MemAddressMode::IndirectIndexedY)),
-    None, None, None,
+    Some((Opcode::JAM, MemAddressMode::Implied)),
+    Some((Opcode::RRA, MemAddressMode::IndirectIndexedY)),
+    Some((Opcode::NOP, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::ADC, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::ROR, MemAddressMode::ZeroPageIndexedX)),
-    None,
+    Some((Opcode::RRA, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::SEI, MemAddressMode::Implied)),
     Some((Opcode::ADC, MemAddressMode::AbsoluteIndexedY)),
-    None, None, None,
+    Some((Opcode::NOP, MemAddressMode::Implied)),
+    Some((Opcode::RRA, MemAddressMode::AbsoluteIndexedY)),
+    Some((Opcode::NOP, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::ADC, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::ROR, MemAddressMode::AbsoluteIndexedX)),
-    None,
+    Some((Opcode::RRA, MemAddressMode::AbsoluteIndexedX)),
 
     //0x80
-    None,
+    Some((Opcode::NOP, MemAddressMode::Immediate)),
     Some((Opcode::STA, MemAddressMode::IndirectIndexedX)),
-    None, None,
+    Some((Opcode::NOP, MemAddressMode::Immediate)),
+    Some((Opcode::SAX, MemAddressMode::IndirectIndexedX)),
     Some((Opcode::STY, MemAddressMode::ZeroPage)),
     Some((Opcode::STA, MemAddressMode::ZeroPage)),
     Some((Opcode::STX, MemAddressMode::ZeroPage)),
-    None,
+    Some((Opcode::SAX, MemAddressMode::ZeroPage)),
     Some((Opcode::DEY, MemAddressMode::Implied)),
-    None,
+    Some((Opcode::NOP, MemAddressMode::Immediate)),
     Some((Opcode::TXA, MemAddressMode::Implied)),
-    None,
+    Some((Opcode::ANE, MemAddressMode::Immediate)),
     Some((Opcode::STY, MemAddressMode::Absolute)),
     Some((Opcode::STA, MemAddressMode::Absolute)),
     Some((Opcode::STX, MemAddressMode::Absolute)),
-    None,
+    Some((Opcode::SAX, MemAddressMode::Absolute)),
 
     //0x90
     Some((Opcode::BCC, MemAddressMode::Relative)),
     Some((Opcode::STA, MemAddressMode::IndirectIndexedY)),
-    None, None,
+    Some((Opcode::JAM, MemAddressMode::Implied)),
+    Some((Opcode::SHA, MemAddressMode::IndirectIndexedY)),
     Some((Opcode::STY, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::STA, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::STX, MemAddressMode::ZeroPageIndexedY)),
-    None,
+    Some((Opcode::SAX, MemAddressMode::ZeroPageIndexedY)),
     Some((Opcode::TYA, MemAddressMode::Implied)),
     Some((Opcode::STA, MemAddressMode::AbsoluteIndexedY)),
     Some((Opcode::TXS, MemAddressMode::Implied)),
-    None, None,
+    Some((Opcode::TAS, MemAddressMode::AbsoluteIndexedY)),
+    Some((Opcode::SHY, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::STA, MemAddressMode::AbsoluteIndexedX)),
-    None, None,
+    Some((Opcode::SHX, MemAddressMode::AbsoluteIndexedY)),
+    Some((Opcode::SHA, MemAddressMode::AbsoluteIndexedY)),
 
     //0xa0
     Some((Opcode::LDY, MemAddressMode::Immediate)),
     Some((Opcode::LDA, MemAddressMode::IndirectIndexedX)),
     Some((Opcode::LDX, MemAddressMode::Immediate)),
-    None,
+    Some((Opcode::LAX, MemAddressMode::IndirectIndexedX)),
     Some((Opcode::LDY, MemAddressMode::ZeroPage)),
     Some((Opcode::LDA, MemAddressMode::ZeroPage)),
     Some((Opcode::LDX, MemAddressMode::ZeroPage)),
-    None,
+    Some((Opcode::LAX, MemAddressMode::ZeroPage)),
     Some((Opcode::TAY, MemAddressMode::Implied)),
     Some((Opcode::LDA, MemAddressMode::Immediate)),
     Some((Opcode::TAX, MemAddressMode::Implied)),
-    None,
+    Some((Opcode::LXA, MemAddressMode::Immediate)),
     Some((Opcode::LDY, MemAddressMode::Absolute)),
     Some((Opcode::LDA, MemAddressMode::Absolute)),
     Some((Opcode::LDX, MemAddressMode::Absolute)),
-    None,
+    Some((Opcode::LAX, MemAddressMode::Absolute)),
 
     //0xb0
     Some((Opcode::BCS, MemAddressMode::Relative)),
     Some((Opcode::LDA, MemAddressMode::IndirectIndexedY)),
-    None, None,
+    Some((Opcode::JAM, MemAddressMode::Implied)),
+    Some((Opcode::LAX, MemAddressMode::IndirectIndexedY)),
     Some((Opcode::LDY, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::LDA, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::LDX, MemAddressMode::ZeroPageIndexedY)),
-    None,
+    Some((Opcode::LAX, MemAddressMode::ZeroPageIndexedY)),
     Some((Opcode::CLV, MemAddressMode::Implied)),
     Some((Opcode::LDA, MemAddressMode::AbsoluteIndexedY)),
     Some((Opcode::TSX, MemAddressMode::Implied)),
-    None,
+    Some((Opcode::LAS, MemAddressMode::AbsoluteIndexedY)),
     Some((Opcode::LDY, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::LDA, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::LDX, MemAddressMode::AbsoluteIndexedY)),
-    None,
+    Some((Opcode::LAX, MemAddressMode::AbsoluteIndexedY)),
 
     //0xc0
     Some((Opcode::CPY, MemAddressMode::Immediate)),
     Some((Opcode::CMP, MemAddressMode::IndirectIndexedX)),
-    None, None,
+    Some((Opcode::NOP, MemAddressMode::Immediate)),
+    Some((Opcode::DCP, MemAddressMode::IndirectIndexedX)),
     Some((Opcode::CPY, MemAddressMode::ZeroPage)),
     Some((Opcode::CMP, MemAddressMode::ZeroPage)),
     Some((Opcode::DEC, MemAddressMode::ZeroPage)),
-    None,
+    Some((Opcode::DCP, MemAddressMode::ZeroPage)),
     Some((Opcode::INY, MemAddressMode::Implied)),
     Some((Opcode::CMP, MemAddressMode::Immediate)),
     Some((Opcode::DEX, MemAddressMode::Implied)),
-    None,
+    Some((Opcode::AXS, MemAddressMode::Immediate)),
     Some((Opcode::CPY, MemAddressMode::Absolute)),
     Some((Opcode::CMP, MemAddressMode::Absolute)),
     Some((Opcode::DEC, MemAddressMode::Absolute)),
-    None,
+    Some((Opcode::DCP, MemAddressMode::Absolute)),
 
     //0xd0
     Some((Opcode::BNE, MemAddressMode::Relative)),
     Some((Opcode::CMP, MemAddressMode::IndirectIndexedY)),
-    None, None, None,
+    Some((Opcode::JAM, MemAddressMode::Implied)),
+    Some((Opcode::DCP, MemAddressMode::IndirectIndexedY)),
+    Some((Opcode::NOP, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::CMP, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::DEC, MemAddressMode::ZeroPageIndexedX)),
-    None,
+    Some((Opcode::DCP, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::CLD, MemAddressMode::Implied)),
     Some((Opcode::CMP, MemAddressMode::AbsoluteIndexedY)),
-    None, None, None,
+    Some((Opcode::NOP, MemAddressMode::Implied)),
+    Some((Opcode::DCP, MemAddressMode::AbsoluteIndexedY)),
+    Some((Opcode::NOP, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::CMP, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::DEC, MemAddressMode::AbsoluteIndexedX)),
-    None,
+    Some((Opcode::DCP, MemAddressMode::AbsoluteIndexedX)),
 
     //0xe0
     Some((Opcode::CPX, MemAddressMode::Immediate)),
     Some((Opcode::SBC, MemAddressMode::IndirectIndexedX)),
-    None, None,
+    Some((Opcode::NOP, MemAddressMode::Immediate)),
+    Some((Opcode::ISC, MemAddressMode::IndirectIndexedX)),
     Some((Opcode::CPX, MemAddressMode::ZeroPage)),
     Some((Opcode::SBC, MemAddressMode::ZeroPage)),
     Some((Opcode::INC, MemAddressMode::ZeroPage)),
-    None,
+    Some((Opcode::ISC, MemAddressMode::ZeroPage)),
     Some((Opcode::INX, MemAddressMode::Implied)),
     Some((Opcode::SBC, MemAddressMode::Immediate)),
     Some((Opcode::NOP, MemAddressMode::Implied)),
-    None,
+    Some((Opcode::SBC, MemAddressMode::Immediate)),
     Some((Opcode::CPX, MemAddressMode::Absolute)),
     Some((Opcode::SBC, MemAddressMode::Absolute)),
     Some((Opcode::INC, MemAddressMode::Absolute)),
-    None,
+    Some((Opcode::ISC, MemAddressMode::Absolute)),
 
     //0xf0
     Some((Opcode::BEQ, MemAddressMode::Relative)),
     Some((Opcode::SBC, MemAddressMode::IndirectIndexedY)),
-    None, None, None,
+    Some((Opcode::JAM, MemAddressMode::Implied)),
+    Some((Opcode::ISC, MemAddressMode::IndirectIndexedY)),
+    Some((Opcode::NOP, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::SBC, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::INC, MemAddressMode::ZeroPageIndexedX)),
-    None,
+    Some((Opcode::ISC, MemAddressMode::ZeroPageIndexedX)),
     Some((Opcode::SED, MemAddressMode::Implied)),
     Some((Opcode::SBC, MemAddressMode::AbsoluteIndexedY)),
-    None, None, None,
+    Some((Opcode::NOP, MemAddressMode::Implied)),
+    Some((Opcode::ISC, MemAddressMode::AbsoluteIndexedY)),
+    Some((Opcode::NOP, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::SBC, MemAddressMode::AbsoluteIndexedX)),
     Some((Opcode::INC, MemAddressMode::AbsoluteIndexedX)),
-    None,
+    Some((Opcode::ISC, MemAddressMode::AbsoluteIndexedX)),
+];
+
+/* Whether an opcode is one of the 151 in the original MOS documentation */
+pub fn is_documented(byte: u8) -> bool {
+    match OPCODES[byte as usize] {
+        Some((Opcode::NOP, _)) => byte == 0xea,
+        Some((Opcode::SBC, _)) => byte != 0xeb,
+        Some((Opcode::ALR | Opcode::ANC | Opcode::ANE | Opcode::ARR | Opcode::AXS
+            | Opcode::DCP | Opcode::ISC | Opcode::JAM | Opcode::LAS | Opcode::LAX
+            | Opcode::LXA | Opcode::RLA | Opcode::RRA | Opcode::SAX | Opcode::SHA
+            | Opcode::SHX | Opcode::SHY | Opcode::SLO | Opcode::SRE | Opcode::TAS, _)) => false,
+        Some(_) => true,
+        None => false,
+    }
+}
+
+/* Base cycle count of every opcode, before page-crossing and branch penalties */
+pub static CYCLES: [u8; 256] = [
+//  0  1  2  3  4  5  6  7  8  9  a  b  c  d  e  f
+    7, 6, 2, 8, 3, 3, 5, 5, 3, 2, 2, 2, 4, 4, 6, 6, // 0x00
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x10
+    6, 6, 2, 8, 3, 3, 5, 5, 4, 2, 2, 2, 4, 4, 6, 6, // 0x20
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x30
+    6, 6, 2, 8, 3, 3, 5, 5, 3, 2, 2, 2, 3, 4, 6, 6, // 0x40
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x50
+    6, 6, 2, 8, 3, 3, 5, 5, 4, 2, 2, 2, 5, 4, 6, 6, // 0x60
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x70
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // 0x80
+    2, 6, 2, 6, 4, 4, 4, 4, 2, 5, 2, 5, 5, 5, 5, 5, // 0x90
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // 0xa0
+    2, 5, 2, 5, 4, 4, 4, 4, 2, 4, 2, 4, 4, 4, 4, 4, // 0xb0
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // 0xc0
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0xd0
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // 0xe0
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0xf0
 ];
@@ -0,0 +1,375 @@
+use super::sprites::SpriteLine;
+use crate::{
+    cartridge::Mirroring,
+    mapper::Mapper,
+    region::Region,
+};
+
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+
+const DOTS_PER_SCANLINE: u16 = 341;
+const SCANLINE_COUNTER_DOT: u16 = 260;  /* Where PPU A12 rises for boards counting scanlines */
+
+/* PPUCTRL ($2000) */
+pub const CTRL_INCREMENT: u8 = 0x04;           /* VRAM address step: 0 = 1, 1 = 32 */
+pub const CTRL_SPRITE_TABLE: u8 = 0x08;        /* 8x8 sprite pattern table: 0 = $0000, 1 = $1000 */
+pub const CTRL_BACKGROUND_TABLE: u8 = 0x10;    /* Background pattern table: 0 = $0000, 1 = $1000 */
+pub const CTRL_SPRITE_SIZE: u8 = 0x20;         /* 0 = 8x8, 1 = 8x16 */
+pub const CTRL_NMI: u8 = 0x80;                 /* Raise NMI at the start of vblank */
+
+/* PPUMASK ($2001) */
+pub const MASK_GREYSCALE: u8 = 0x01;
+pub const MASK_BACKGROUND_LEFT: u8 = 0x02;     /* Show background in the leftmost 8 pixels */
+pub const MASK_SPRITES_LEFT: u8 = 0x04;        /* Show sprites in the leftmost 8 pixels */
+pub const MASK_BACKGROUND: u8 = 0x08;
+pub const MASK_SPRITES: u8 = 0x10;
+
+/* PPUSTATUS ($2002) */
+pub const STATUS_OVERFLOW: u8 = 0x20;
+pub const STATUS_SPRITE_ZERO: u8 = 0x40;
+pub const STATUS_VBLANK: u8 = 0x80;
+
+/* The 2C02. Internal scroll registers follow loopy's naming:
+ *  v, t  15-bit VRAM address, yyy NN YYYYY XXXXX (fine Y, nametable, coarse Y, coarse X)
+ *  x     fine X scroll
+ *  w     first/second write toggle shared by $2005 and $2006 */
+pub struct Ppu {
+    pub ctrl: u8,
+    pub mask: u8,
+    pub status: u8,
+    pub oam_addr: u8,
+    pub oam: [u8; 0x100],
+    pub scanline: u16,
+    pub dot: u16,
+    pub frame_count: u64,
+    pub region: Region,         /* Picks the frame length and where vblank starts */
+    pub frame: Box<[u8; SCREEN_WIDTH * SCREEN_HEIGHT]>,   /* Palette RAM values, one per pixel */
+    frame_ready: bool,
+    vram: [u8; 0x1000],         /* 2 KB on the console, the rest for four-screen boards */
+    palette: [u8; 0x20],
+    pub(super) v: u16,
+    pub(super) t: u16,
+    pub(super) x: u8,
+    pub(super) w: bool,
+    pub(super) read_buffer: u8,
+    pub(super) latch: u8,       /* Last value driven on the CPU-facing data bus */
+
+    /* Background pipeline: the next tile's fetched bytes, then 16-bit shifters holding two tiles */
+    tile_id: u8,
+    tile_attribute: u8,
+    tile_low: u8,
+    tile_high: u8,
+    pattern_low: u16,
+    pattern_high: u16,
+    attribute_low: u16,
+    attribute_high: u16,
+
+    pub(super) sprites: SpriteLine,
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ppu {
+    pub fn new() -> Self {
+        Ppu {
+            ctrl: 0,
+            mask: 0,
+            status: 0,
+            oam_addr: 0,
+            oam: [0; 0x100],
+            scanline: 0,
+            dot: 0,
+            frame_count: 0,
+            region: Region::default(),
+            frame: Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT]),
+            frame_ready: false,
+            vram: [0; 0x1000],
+            palette: [0; 0x20],
+            v: 0,
+            t: 0,
+            x: 0,
+            w: false,
+            read_buffer: 0,
+            latch: 0,
+            tile_id: 0,
+            tile_attribute: 0,
+            tile_low: 0,
+            tile_high: 0,
+            pattern_low: 0,
+            pattern_high: 0,
+            attribute_low: 0,
+            attribute_high: 0,
+            sprites: SpriteLine::default(),
+        }
+    }
+
+    /* Level of the PPU's /NMI output, true when asserted */
+    pub fn nmi(&self) -> bool {
+        self.status & STATUS_VBLANK != 0 && self.ctrl & CTRL_NMI != 0
+    }
+
+    /* True once per frame, when vblank starts and `frame` holds a finished picture */
+    pub fn take_frame(&mut self) -> bool {
+        let ready = self.frame_ready;
+        self.frame_ready = false;
+        ready
+    }
+
+    /* The last line of the frame, which primes the first visible one */
+    fn pre_render_scanline(&self) -> u16 {
+        self.region.scanlines() - 1
+    }
+
+    pub fn rendering_enabled(&self) -> bool {
+        self.mask & (MASK_BACKGROUND | MASK_SPRITES) != 0
+    }
+
+    /* Runs a single dot */
+    pub fn step(&mut self, mapper: &mut Option<Box<dyn Mapper>>) {
+        let visible = self.scanline < SCREEN_HEIGHT as u16;
+        let pre_render = self.scanline == self.pre_render_scanline();
+
+        if pre_render && self.dot == 1 {
+            self.status &= !(STATUS_VBLANK | STATUS_SPRITE_ZERO | STATUS_OVERFLOW);
+        }
+
+        if (visible || pre_render) && self.rendering_enabled() {
+            self.background_cycle(mapper);
+
+            if self.dot == 257 {
+                self.evaluate_sprites(mapper, pre_render);
+            }
+            if (257..=320).contains(&self.dot) {
+                self.oam_addr = 0;
+            }
+
+            if self.dot == SCANLINE_COUNTER_DOT {
+                if let Some(mapper) = mapper {
+                    mapper.notify_scanline();
+                }
+            }
+        }
+
+        if visible && (1..=SCREEN_WIDTH as u16).contains(&self.dot) {
+            self.render_pixel();
+        }
+
+        if self.scanline == self.region.vblank_scanline() && self.dot == 1 {
+            self.status |= STATUS_VBLANK;
+            self.frame_ready = true;
+        }
+
+        self.advance();
+    }
+
+    fn advance(&mut self) {
+        self.dot += 1;
+
+        /* Odd frames skip the last dot of the pre-render line while rendering */
+        if self.scanline == self.pre_render_scanline() && self.dot == DOTS_PER_SCANLINE - 1
+            && self.frame_count & 1 == 1 && self.rendering_enabled() && self.region.skips_odd_dot() {
+            self.dot = DOTS_PER_SCANLINE;
+        }
+
+        if self.dot >= DOTS_PER_SCANLINE {
+            self.dot = 0;
+            self.scanline += 1;
+            if self.scanline > self.pre_render_scanline() {
+                self.scanline = 0;
+                self.frame_count += 1;
+            }
+        }
+    }
+
+    /* Background fetches{{{1*/
+    /* Each tile takes 8 dots: nametable, attribute, pattern low and pattern high bytes
+     * two dots apiece. Dots 321-336 prefetch the first two tiles of the next line. */
+    fn background_cycle(&mut self, mapper: &mut Option<Box<dyn Mapper>>) {
+        let dot = self.dot;
+
+        if (2..=257).contains(&dot) || (322..=337).contains(&dot) {
+            self.shift_background();
+        }
+
+        if (1..=256).contains(&dot) || (321..=336).contains(&dot) {
+            match (dot - 1) % 8 {
+                0 => {
+                    self.load_background();
+                    self.tile_id = self.read_vram(mapper, 0x2000 | (self.v & 0x0fff));
+                },
+                2 => {
+                    let addr = 0x23c0 | (self.v & 0x0c00) | ((self.v >> 4) & 0x38) | ((self.v >> 2) & 0x07);
+                    let shift = ((self.v >> 4) & 0x04) | (self.v & 0x02);
+                    self.tile_attribute = (self.read_vram(mapper, addr) >> shift) & 0x03;
+                },
+                4 => self.tile_low = self.read_vram(mapper, self.pattern_address()),
+                6 => self.tile_high = self.read_vram(mapper, self.pattern_address() + 8),
+                7 => self.increment_x(),
+                _ => {},
+            }
+        }
+
+        match dot {
+            256 => self.increment_y(),
+            257 => {
+                self.load_background();
+                self.v = (self.v & !0x041f) | (self.t & 0x041f);
+            },
+            337 => self.load_background(),
+            338 | 340 => self.tile_id = self.read_vram(mapper, 0x2000 | (self.v & 0x0fff)),
+            _ => {},
+        }
+
+        if self.scanline == self.pre_render_scanline() && (280..=304).contains(&dot) {
+            self.v = (self.v & !0x7be0) | (self.t & 0x7be0);
+        }
+    }
+
+    fn pattern_address(&self) -> u16 {
+        let table = if self.ctrl & CTRL_BACKGROUND_TABLE != 0 { 0x1000 } else { 0 };
+        table | ((self.tile_id as u16) << 4) | ((self.v >> 12) & 0x07)
+    }
+
+    fn load_background(&mut self) {
+        self.pattern_low = (self.pattern_low & 0xff00) | self.tile_low as u16;
+        self.pattern_high = (self.pattern_high & 0xff00) | self.tile_high as u16;
+        self.attribute_low = (self.attribute_low & 0xff00) | if self.tile_attribute & 1 != 0 { 0xff } else { 0 };
+        self.attribute_high = (self.attribute_high & 0xff00) | if self.tile_attribute & 2 != 0 { 0xff } else { 0 };
+    }
+
+    fn shift_background(&mut self) {
+        self.pattern_low <<= 1;
+        self.pattern_high <<= 1;
+        self.attribute_low <<= 1;
+        self.attribute_high <<= 1;
+    }
+
+    fn increment_x(&mut self) {
+        if self.v & 0x001f == 31 {
+            self.v &= !0x001f;
+            self.v ^= 0x0400;   /* Next horizontal nametable */
+        }
+        else {
+            self.v += 1;
+        }
+    }
+
+    fn increment_y(&mut self) {
+        if self.v & 0x7000 != 0x7000 {
+            self.v += 0x1000;
+            return;
+        }
+
+        self.v &= !0x7000;
+        let coarse_y = match (self.v & 0x03e0) >> 5 {
+            29 => {
+                self.v ^= 0x0800;   /* Next vertical nametable */
+                0
+            },
+            31 => 0,                /* Attribute rows wrap without switching nametables */
+            y => y + 1,
+        };
+        self.v = (self.v & !0x03e0) | (coarse_y << 5);
+    }
+
+    /* Returns the 2-bit pixel and palette number under fine X */
+    fn background_pixel(&self) -> (u8, u8) {
+        let bit = 0x8000 >> self.x;
+        let pixel = ((self.pattern_high & bit != 0) as u8) << 1 | (self.pattern_low & bit != 0) as u8;
+        let palette = ((self.attribute_high & bit != 0) as u8) << 1 | (self.attribute_low & bit != 0) as u8;
+        (pixel, palette)
+    }
+
+    //}}}1
+
+    fn render_pixel(&mut self) {
+        let x = (self.dot - 1) as usize;
+        let y = self.scanline as usize;
+
+        let (pixel, palette) = if self.mask & MASK_BACKGROUND != 0 && (x >= 8 || self.mask & MASK_BACKGROUND_LEFT != 0) {
+            self.background_pixel()
+        }
+        else {
+            (0, 0)
+        };
+
+        let background = 0x3f00 | ((palette as u16) << 2) | pixel as u16;
+        let addr = match self.sprite_pixel(x) {
+            Some(sprite) => {
+                /* Sprite 0 hit never happens at x = 255 */
+                if sprite.sprite_zero && pixel != 0 && x != SCREEN_WIDTH - 1 {
+                    self.status |= STATUS_SPRITE_ZERO;
+                }
+
+                if pixel != 0 && sprite.behind_background {
+                    background
+                }
+                else {
+                    0x3f00 | ((sprite.palette as u16) << 2) | sprite.pixel as u16
+                }
+            },
+            None if pixel == 0 => 0x3f00,
+            None => background,
+        };
+        self.frame[y * SCREEN_WIDTH + x] = self.read_palette(addr);
+    }
+
+    /* PPU memory{{{1*/
+    /* PPU address map:
+     *  $0000-$1FFF  Pattern tables, on the cartridge
+     *  $2000-$2FFF  Nametables, mirrored through $3EFF
+     *  $3F00-$3F1F  Palette RAM, mirrored through $3FFF */
+    pub(super) fn read_vram(&self, mapper: &Option<Box<dyn Mapper>>, addr: u16) -> u8 {
+        let addr = addr & 0x3fff;
+        match addr {
+            0x0000..=0x1fff => mapper.as_ref().map_or(0, |mapper| mapper.ppu_read(addr)),
+            0x2000..=0x3eff => self.vram[Ppu::nametable_index(mapper, addr)],
+            _ => self.read_palette(addr),
+        }
+    }
+
+    pub(super) fn write_vram(&mut self, mapper: &mut Option<Box<dyn Mapper>>, addr: u16, byte: u8) {
+        let addr = addr & 0x3fff;
+        match addr {
+            0x0000..=0x1fff => {
+                if let Some(mapper) = mapper {
+                    mapper.ppu_write(addr, byte);
+                }
+            },
+            0x2000..=0x3eff => self.vram[Ppu::nametable_index(mapper, addr)] = byte,
+            _ => self.palette[Ppu::palette_index(addr)] = byte & 0x3f,
+        }
+    }
+
+    pub(super) fn read_palette(&self, addr: u16) -> u8 {
+        let color = self.palette[Ppu::palette_index(addr)];
+        if self.mask & MASK_GREYSCALE != 0 { color & 0x30 } else { color }
+    }
+
+    /* $3F10/$3F14/$3F18/$3F1C are mirrors of the background entries below them */
+    fn palette_index(addr: u16) -> usize {
+        let index = addr as usize & 0x1f;
+        if index & 0x13 == 0x10 { index & !0x10 } else { index }
+    }
+
+    fn nametable_index(mapper: &Option<Box<dyn Mapper>>, addr: u16) -> usize {
+        let mirroring = mapper.as_ref().map_or(Mirroring::Horizontal, |mapper| mapper.mirroring());
+        let table = (addr as usize >> 10) & 0x03;
+        let offset = addr as usize & 0x03ff;
+
+        let page = match mirroring {
+            Mirroring::Horizontal => table >> 1,
+            Mirroring::Vertical => table & 1,
+            Mirroring::SingleScreenLower => 0,
+            Mirroring::SingleScreenUpper => 1,
+            Mirroring::FourScreen => table,
+        };
+
+        (page << 10) | offset
+    }
+}
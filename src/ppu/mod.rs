@@ -0,0 +1,20 @@
+mod core;
+mod registers;
+mod sprites;
+mod palette;
+
+pub use self::core::{
+    Ppu,
+    SCREEN_WIDTH,
+    SCREEN_HEIGHT,
+};
+
+pub use palette::PALETTE;
+pub use sprites::{
+    SpriteLine,
+    SpritePixel,
+    MAX_SPRITES_PER_LINE,
+};
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,103 @@
+use super::core::{
+    Ppu,
+    CTRL_INCREMENT,
+    STATUS_VBLANK,
+};
+use crate::mapper::Mapper;
+
+/* CPU-facing registers, $2000-$2007. Bits a register does not drive read back as
+ * whatever was last on the PPU data bus. */
+impl Ppu {
+    pub fn read_register(&mut self, mapper: &Option<Box<dyn Mapper>>, reg: u16) -> u8 {
+        let byte = match reg & 0x07 {
+            2 => {
+                let status = (self.status & 0xe0) | (self.latch & 0x1f);
+                self.status &= !STATUS_VBLANK;
+                self.w = false;
+                status
+            },
+            4 => self.oam[self.oam_addr as usize],
+            7 => self.read_data(mapper),
+            _ => self.latch,
+        };
+
+        self.latch = byte;
+        byte
+    }
+
+    /* What a read would return, without clearing flags or advancing the address */
+    pub fn peek_register(&self, reg: u16) -> u8 {
+        match reg & 0x07 {
+            2 => (self.status & 0xe0) | (self.latch & 0x1f),
+            4 => self.oam[self.oam_addr as usize],
+            7 => self.read_buffer,
+            _ => self.latch,
+        }
+    }
+
+    pub fn write_register(&mut self, mapper: &mut Option<Box<dyn Mapper>>, reg: u16, byte: u8) {
+        self.latch = byte;
+
+        match reg & 0x07 {
+            0 => {
+                self.ctrl = byte;
+                self.t = (self.t & !0x0c00) | ((byte as u16 & 0x03) << 10);
+            },
+            1 => self.mask = byte,
+            3 => self.oam_addr = byte,
+            4 => {
+                /* Bits 2-4 of the attribute byte do not exist */
+                let byte = if self.oam_addr & 0x03 == 2 { byte & 0xe3 } else { byte };
+                self.oam[self.oam_addr as usize] = byte;
+                self.oam_addr = self.oam_addr.wrapping_add(1);
+            },
+            5 if !self.w => {
+                self.x = byte & 0x07;
+                self.t = (self.t & !0x001f) | (byte as u16 >> 3);
+                self.w = true;
+            },
+            5 => {
+                self.t = (self.t & !0x73e0) | ((byte as u16 & 0x07) << 12) | ((byte as u16 & 0xf8) << 2);
+                self.w = false;
+            },
+            6 if !self.w => {
+                self.t = (self.t & 0x00ff) | ((byte as u16 & 0x3f) << 8);
+                self.w = true;
+            },
+            6 => {
+                self.t = (self.t & 0xff00) | byte as u16;
+                self.v = self.t;
+                self.w = false;
+            },
+            7 => {
+                self.write_vram(mapper, self.v, byte);
+                self.increment_address();
+            },
+            _ => {},    /* $2002 is read-only */
+        }
+    }
+
+    /* $2007 reads below the palette come from a one-byte buffer that is refilled
+     * afterwards. Palette reads are immediate but still refill the buffer, with the
+     * nametable byte underneath. */
+    fn read_data(&mut self, mapper: &Option<Box<dyn Mapper>>) -> u8 {
+        let addr = self.v & 0x3fff;
+        let byte = if addr >= 0x3f00 {
+            self.read_buffer = self.read_vram(mapper, addr - 0x1000);
+            self.read_palette(addr) | (self.latch & 0xc0)
+        }
+        else {
+            let buffered = self.read_buffer;
+            self.read_buffer = self.read_vram(mapper, addr);
+            buffered
+        };
+
+        self.increment_address();
+        byte
+    }
+
+    fn increment_address(&mut self) {
+        let step = if self.ctrl & CTRL_INCREMENT != 0 { 32 } else { 1 };
+        self.v = self.v.wrapping_add(step) & 0x7fff;
+    }
+}
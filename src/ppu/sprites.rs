@@ -0,0 +1,152 @@
+use super::core::{
+    Ppu,
+    CTRL_SPRITE_TABLE,
+    CTRL_SPRITE_SIZE,
+    MASK_SPRITES,
+    MASK_SPRITES_LEFT,
+    STATUS_OVERFLOW,
+};
+use crate::mapper::Mapper;
+
+pub const MAX_SPRITES_PER_LINE: usize = 8;
+
+/* OAM attribute byte */
+const ATTR_PALETTE: u8 = 0x03;
+const ATTR_BEHIND_BACKGROUND: u8 = 0x20;
+const ATTR_FLIP_HORIZONTAL: u8 = 0x40;
+const ATTR_FLIP_VERTICAL: u8 = 0x80;
+
+/* The sprites found by evaluation, with their pattern rows fetched, for the next scanline */
+#[derive(Default)]
+pub struct SpriteLine {
+    pub secondary_oam: [u8; MAX_SPRITES_PER_LINE * 4],
+    pub count: usize,
+    pub zero_on_line: bool,     /* Slot 0 holds OAM sprite 0 */
+    pub x: [u8; MAX_SPRITES_PER_LINE],
+    pub attributes: [u8; MAX_SPRITES_PER_LINE],
+    pub pattern_low: [u8; MAX_SPRITES_PER_LINE],     /* Already flipped horizontally */
+    pub pattern_high: [u8; MAX_SPRITES_PER_LINE],
+}
+
+/* An opaque sprite pixel that won priority among the sprites at one X */
+pub struct SpritePixel {
+    pub pixel: u8,
+    pub palette: u8,
+    pub behind_background: bool,
+    pub sprite_zero: bool,
+}
+
+impl Ppu {
+    pub fn sprite_height(&self) -> u16 {
+        if self.ctrl & CTRL_SPRITE_SIZE != 0 { 16 } else { 8 }
+    }
+
+    /* Runs at dot 257: picks the sprites of the current scanline that appear on the next
+     * one, then performs the pattern fetches of dots 257-320 */
+    pub(super) fn evaluate_sprites(&mut self, mapper: &Option<Box<dyn Mapper>>, pre_render: bool) {
+        self.sprites.secondary_oam = [0xff; MAX_SPRITES_PER_LINE * 4];
+        self.sprites.count = 0;
+        self.sprites.zero_on_line = false;
+
+        if !pre_render {
+            self.find_sprites();
+        }
+        self.fetch_sprites(mapper);
+    }
+
+    fn sprite_in_range(&self, y: u8) -> bool {
+        let row = self.scanline.wrapping_sub(y as u16);
+        row < self.sprite_height()
+    }
+
+    fn find_sprites(&mut self) {
+        let mut n = 0;
+        while n < 64 && self.sprites.count < MAX_SPRITES_PER_LINE {
+            if self.sprite_in_range(self.oam[n * 4]) {
+                let slot = self.sprites.count * 4;
+                self.sprites.secondary_oam[slot..(slot + 4)].copy_from_slice(&self.oam[(n * 4)..(n * 4 + 4)]);
+                self.sprites.zero_on_line |= n == 0;
+                self.sprites.count += 1;
+            }
+            n += 1;
+        }
+
+        /* With eight sprites found the hardware keeps scanning for a ninth, but a bug makes
+         * it step the byte index along with the sprite index, so it compares tile numbers,
+         * attributes and X positions as if they were Y coordinates. */
+        let mut m = 0;
+        while n < 64 {
+            if self.sprite_in_range(self.oam[n * 4 + m]) {
+                self.status |= STATUS_OVERFLOW;
+                break;
+            }
+            n += 1;
+            m = (m + 1) & 0x03;
+        }
+    }
+
+    fn fetch_sprites(&mut self, mapper: &Option<Box<dyn Mapper>>) {
+        let height = self.sprite_height();
+
+        /* Empty slots still fetch tile $FF, which boards watching PPU A12 can see */
+        for slot in 0..MAX_SPRITES_PER_LINE {
+            let entry = &self.sprites.secondary_oam[(slot * 4)..(slot * 4 + 4)];
+            let (y, tile, attributes, x) = (entry[0], entry[1], entry[2], entry[3]);
+
+            let mut row = if slot < self.sprites.count { self.scanline.wrapping_sub(y as u16) } else { 0 };
+            if attributes & ATTR_FLIP_VERTICAL != 0 && slot < self.sprites.count {
+                row = height - 1 - row;
+            }
+
+            let addr = if height == 16 {
+                let table = (tile as u16 & 1) << 12;
+                let tile = (tile as u16 & 0xfe) + (row >> 3);
+                table | (tile << 4) | (row & 0x07)
+            }
+            else {
+                let table = if self.ctrl & CTRL_SPRITE_TABLE != 0 { 0x1000 } else { 0 };
+                table | ((tile as u16) << 4) | row
+            };
+
+            let mut low = self.read_vram(mapper, addr);
+            let mut high = self.read_vram(mapper, addr + 8);
+            if attributes & ATTR_FLIP_HORIZONTAL != 0 {
+                low = low.reverse_bits();
+                high = high.reverse_bits();
+            }
+
+            self.sprites.x[slot] = x;
+            self.sprites.attributes[slot] = attributes;
+            self.sprites.pattern_low[slot] = low;
+            self.sprites.pattern_high[slot] = high;
+        }
+    }
+
+    /* The first opaque sprite in OAM order wins, whatever its background priority */
+    pub(super) fn sprite_pixel(&self, x: usize) -> Option<SpritePixel> {
+        if self.mask & MASK_SPRITES == 0 || (x < 8 && self.mask & MASK_SPRITES_LEFT == 0) {
+            return None;
+        }
+
+        for slot in 0..self.sprites.count {
+            let offset = x.wrapping_sub(self.sprites.x[slot] as usize);
+            if offset >= 8 {
+                continue;
+            }
+
+            let bit = 7 - offset;
+            let pixel = ((self.sprites.pattern_high[slot] >> bit) & 1) << 1 | ((self.sprites.pattern_low[slot] >> bit) & 1);
+            if pixel != 0 {
+                let attributes = self.sprites.attributes[slot];
+                return Some(SpritePixel {
+                    pixel,
+                    palette: (attributes & ATTR_PALETTE) + 4,
+                    behind_background: attributes & ATTR_BEHIND_BACKGROUND != 0,
+                    sprite_zero: slot == 0 && self.sprites.zero_on_line,
+                });
+            }
+        }
+
+        None
+    }
+}
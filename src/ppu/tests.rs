@@ -0,0 +1,301 @@
+use super::{
+    Ppu,
+    core::{CTRL_NMI, STATUS_OVERFLOW, STATUS_SPRITE_ZERO, STATUS_VBLANK},
+};
+use crate::{
+    cartridge::test_image::TestImage,
+    mapper::{self, Mapper},
+    region::Region,
+};
+
+/* Steps until `dot` of `scanline` is the next one to run */
+fn run_to(ppu: &mut Ppu, scanline: u16, dot: u16) {
+    while (ppu.scanline, ppu.dot) != (scanline, dot) {
+        ppu.step(&mut None);
+    }
+}
+
+/* Registers{{{1*/
+#[test]
+fn scroll_writes_fill_t_and_fine_x() {
+    let mut ppu = Ppu::new();
+    ppu.write_register(&mut None, 0x2000, 0x02);    /* Nametable 2 */
+
+    ppu.write_register(&mut None, 0x2005, 0x7d);    /* Coarse X 15, fine X 5 */
+    assert_eq!((ppu.t, ppu.x, ppu.w), (0x080f, 5, true));
+
+    ppu.write_register(&mut None, 0x2005, 0x5e);    /* Coarse Y 11, fine Y 6 */
+    assert_eq!((ppu.t, ppu.x, ppu.w), (0x696f, 5, false));
+    assert_eq!(ppu.v, 0, "$2005 never touches v");
+}
+
+#[test]
+fn address_writes_copy_t_to_v_on_the_second_write() {
+    let mut ppu = Ppu::new();
+    ppu.write_register(&mut None, 0x2005, 0x07);    /* Fine X 7, which $2006 leaves alone */
+    ppu.write_register(&mut None, 0x2005, 0x07);    /* Fine Y 7, in bits 12-14 of t */
+    assert_eq!(ppu.t, 0x7000);
+
+    ppu.write_register(&mut None, 0x2006, 0x23);    /* The first write also clears bit 14 */
+    assert_eq!((ppu.t, ppu.v, ppu.w), (0x2300, 0, true));
+    ppu.write_register(&mut None, 0x2006, 0xc5);
+    assert_eq!((ppu.t, ppu.v, ppu.x, ppu.w), (0x23c5, 0x23c5, 7, false));
+
+    /* Only the low six bits of the high byte are kept */
+    ppu.write_register(&mut None, 0x2006, 0xff);
+    ppu.write_register(&mut None, 0x2006, 0xff);
+    assert_eq!(ppu.v, 0x3fff);
+}
+
+#[test]
+fn status_read_clears_vblank_and_the_write_toggle() {
+    let mut ppu = Ppu::new();
+    ppu.status = STATUS_VBLANK;
+    ppu.write_register(&mut None, 0x2006, 0x21);    /* Leaves the latch at $21 */
+    assert!(ppu.w);
+
+    assert_eq!(ppu.read_register(&None, 0x2002), 0x81, "the low bits come from the data bus");
+    assert!(!ppu.w);
+    assert_eq!(ppu.status & STATUS_VBLANK, 0);
+    assert_eq!(ppu.read_register(&None, 0x2002) & STATUS_VBLANK, 0);
+
+    /* After the read, $2006 starts again from the high byte */
+    ppu.write_register(&mut None, 0x2006, 0x3f);
+    ppu.write_register(&mut None, 0x2006, 0x10);
+    assert_eq!(ppu.v, 0x3f10);
+}
+
+#[test]
+fn peek_leaves_status_alone() {
+    let mut ppu = Ppu::new();
+    ppu.status = STATUS_VBLANK;
+    ppu.w = true;
+
+    assert_eq!(ppu.peek_register(0x2002) & STATUS_VBLANK, STATUS_VBLANK);
+    assert_eq!(ppu.status, STATUS_VBLANK);
+    assert!(ppu.w);
+}
+//}}}1
+
+/* VBlank and NMI{{{1*/
+#[test]
+fn vblank_starts_at_dot_1_of_line_241() {
+    let mut ppu = Ppu::new();
+    ppu.ctrl = CTRL_NMI;
+
+    run_to(&mut ppu, 241, 1);
+    assert_eq!(ppu.status & STATUS_VBLANK, 0);
+    assert!(!ppu.nmi());
+
+    ppu.step(&mut None);
+    assert_eq!(ppu.status & STATUS_VBLANK, STATUS_VBLANK);
+    assert!(ppu.nmi());
+    assert!(ppu.take_frame());
+}
+
+#[test]
+fn vblank_ends_at_dot_1_of_the_pre_render_line() {
+    let mut ppu = Ppu::new();
+    run_to(&mut ppu, 261, 1);
+    assert_eq!(ppu.status & STATUS_VBLANK, STATUS_VBLANK);
+
+    ppu.step(&mut None);
+    assert_eq!(ppu.status & STATUS_VBLANK, 0);
+}
+
+#[test]
+fn nmi_follows_ctrl_and_status() {
+    let mut ppu = Ppu::new();
+    run_to(&mut ppu, 241, 2);
+    assert!(!ppu.nmi(), "NMI is disabled in $2000");
+
+    /* Enabling NMI in the middle of vblank raises it straight away */
+    ppu.write_register(&mut None, 0x2000, CTRL_NMI);
+    assert!(ppu.nmi());
+    ppu.write_register(&mut None, 0x2000, 0x00);
+    assert!(!ppu.nmi());
+    ppu.write_register(&mut None, 0x2000, CTRL_NMI);
+    assert!(ppu.nmi());
+
+    /* Reading $2002 acknowledges it */
+    ppu.read_register(&None, 0x2002);
+    assert!(!ppu.nmi());
+}
+
+#[test]
+fn dendy_vblank_starts_late() {
+    let mut ppu = Ppu::new();
+    ppu.region = Region::Dendy;
+
+    run_to(&mut ppu, 242, 0);
+    assert_eq!(ppu.status & STATUS_VBLANK, 0);
+    run_to(&mut ppu, 291, 2);
+    assert_eq!(ppu.status & STATUS_VBLANK, STATUS_VBLANK);
+}
+//}}}1
+
+/* Background{{{1*/
+/* Writes `bytes` to VRAM from `addr` through $2006/$2007 */
+fn write_vram(ppu: &mut Ppu, mapper: &mut Option<Box<dyn Mapper>>, addr: u16, bytes: &[u8]) {
+    ppu.write_register(mapper, 0x2006, (addr >> 8) as u8);
+    ppu.write_register(mapper, 0x2006, addr as u8);
+    for &byte in bytes {
+        ppu.write_register(mapper, 0x2007, byte);
+    }
+}
+
+#[test]
+fn background_tile_is_drawn_with_its_attribute_and_fine_x() {
+    /* Tile 1 has pixel values 3, 1, 2, 0 repeating across every row */
+    let mut mapper = Some(mapper::from_cartridge(TestImage::new(0, 1, 0).cartridge()).unwrap());
+    for row in 0..8 {
+        mapper.as_mut().unwrap().ppu_write(0x10 + row, 0xcc);
+        mapper.as_mut().unwrap().ppu_write(0x18 + row, 0xaa);
+    }
+
+    let mut ppu = Ppu::new();
+    ppu.oam = [0xff; 0x100];
+    write_vram(&mut ppu, &mut mapper, 0x2000, &[1, 0, 1]);         /* Tile 1 in columns 0 and 2 */
+    write_vram(&mut ppu, &mut mapper, 0x23c0, &[0x08]);            /* Palette 2 for columns 2 and 3 */
+    write_vram(&mut ppu, &mut mapper, 0x3f00, &[0x0f, 0x01, 0x02, 0x03, 0x0f, 0x04, 0x05, 0x06, 0x0f, 0x11, 0x22, 0x33]);
+
+    /* $2006 left nametable 3 in t, so $2000 has to pick nametable 0 again */
+    ppu.write_register(&mut mapper, 0x2000, 0x00);
+    ppu.read_register(&mapper, 0x2002);
+    ppu.write_register(&mut mapper, 0x2005, 0x03);                 /* Fine X 3 */
+    ppu.write_register(&mut mapper, 0x2005, 0x00);
+    ppu.write_register(&mut mapper, 0x2001, 0x0a);                 /* Background, left column included */
+
+    /* The pre-render line loads v from t and fetches the first two tiles */
+    run_to(&mut ppu, 261, 0);
+    while (ppu.scanline, ppu.dot) != (8, 0) {
+        ppu.step(&mut mapper);
+    }
+
+    let mut expected = [0x0f; 32];
+    expected[1..4].copy_from_slice(&[0x03, 0x01, 0x02]);           /* Columns 3-7 of the first tile */
+    expected[13..21].copy_from_slice(&[0x33, 0x11, 0x22, 0x0f, 0x33, 0x11, 0x22, 0x0f]);
+    for y in 0..8 {
+        assert_eq!(ppu.frame[y * 256..(y * 256 + 32)], expected, "line {}", y);
+    }
+}
+
+/* Sprites{{{1*/
+/* An NROM board with CHR RAM, whose first `solid_tiles` tiles have every pixel set */
+fn board_with_solid_tiles(solid_tiles: u16) -> Option<Box<dyn Mapper>> {
+    let mut board = mapper::from_cartridge(TestImage::new(0, 1, 0).cartridge()).unwrap();
+
+    for addr in 0..(solid_tiles * 16) {
+        board.ppu_write(addr, if addr & 0x08 == 0 { 0xff } else { 0x00 });
+    }
+    Some(board)
+}
+
+/* Evaluates OAM on `scanline`, which looks for sprites to draw on the line after it */
+fn evaluate(ppu: &mut Ppu, scanline: u16) {
+    ppu.scanline = scanline;
+    ppu.evaluate_sprites(&None, false);
+}
+
+#[test]
+fn eight_sprites_fill_a_line_without_overflow() {
+    let mut ppu = Ppu::new();
+    ppu.oam = [0xff; 0x100];
+    for n in 0..8 {
+        ppu.oam[n * 4] = 20;
+    }
+
+    evaluate(&mut ppu, 20);
+    assert_eq!(ppu.sprites.count, 8);
+    assert!(ppu.sprites.zero_on_line);
+    assert_eq!(ppu.status & STATUS_OVERFLOW, 0);
+}
+
+#[test]
+fn ninth_sprite_sets_overflow() {
+    let mut ppu = Ppu::new();
+    ppu.oam = [0xff; 0x100];
+    for n in 1..10 {
+        ppu.oam[n * 4] = 15;        /* Rows 5 and 6 of sprites starting at line 15 */
+    }
+
+    evaluate(&mut ppu, 20);
+    assert_eq!(ppu.sprites.count, 8);
+    assert!(!ppu.sprites.zero_on_line);
+    assert_eq!(ppu.sprites.secondary_oam[0], 15);
+    assert_eq!(ppu.status & STATUS_OVERFLOW, STATUS_OVERFLOW);
+}
+
+/* After the eighth sprite the scan reads byte m of sprite n, stepping both, so a ninth
+ * sprite whose Y is not the byte looked at goes unnoticed... */
+#[test]
+fn overflow_scan_misses_a_ninth_sprite_off_the_diagonal() {
+    let mut ppu = Ppu::new();
+    ppu.oam = [0xff; 0x100];
+    for n in 0..8 {
+        ppu.oam[n * 4] = 20;
+    }
+    ppu.oam[9 * 4] = 20;            /* Sprite 8 is skipped at byte 0, sprite 9 is read at byte 1 */
+
+    evaluate(&mut ppu, 20);
+    assert_eq!(ppu.sprites.count, 8);
+    assert_eq!(ppu.status & STATUS_OVERFLOW, 0);
+}
+
+/* ...and a tile number, attribute or X that happens to be in range counts as a sprite */
+#[test]
+fn overflow_scan_mistakes_a_tile_number_for_y() {
+    let mut ppu = Ppu::new();
+    ppu.oam = [0xff; 0x100];
+    for n in 0..8 {
+        ppu.oam[n * 4] = 20;
+    }
+    ppu.oam[9 * 4 + 1] = 18;        /* Tile number of sprite 9, whose Y is $FF */
+
+    evaluate(&mut ppu, 20);
+    assert_eq!(ppu.sprites.count, 8);
+    assert_eq!(ppu.status & STATUS_OVERFLOW, STATUS_OVERFLOW);
+}
+
+#[test]
+fn sprite_zero_hits_on_its_first_opaque_pixel_over_background() {
+    let mut mapper = board_with_solid_tiles(1);     /* Tile 0 fills both background and sprite */
+    let mut ppu = Ppu::new();
+    ppu.mask = 0x1e;
+    ppu.oam = [0xff; 0x100];
+    ppu.oam[0..4].copy_from_slice(&[30, 0, 0, 100]);
+
+    while (ppu.scanline, ppu.dot) != (31, 101) {
+        ppu.step(&mut mapper);
+    }
+    assert_eq!(ppu.status & STATUS_SPRITE_ZERO, 0);
+
+    ppu.step(&mut mapper);                          /* Draws x = 100 of line 31 */
+    assert_eq!(ppu.status & STATUS_SPRITE_ZERO, STATUS_SPRITE_ZERO);
+
+    while (ppu.scanline, ppu.dot) != (261, 2) {
+        ppu.step(&mut mapper);
+    }
+    assert_eq!(ppu.status & STATUS_SPRITE_ZERO, 0, "the pre-render line clears the hit");
+}
+
+#[test]
+fn sprite_zero_misses_over_transparent_background() {
+    let mut mapper = board_with_solid_tiles(1);
+    let mut ppu = Ppu::new();
+
+    /* Point the background at empty tile 1 */
+    write_vram(&mut ppu, &mut mapper, 0x2000, &[1; 0x3c0]);
+    ppu.write_register(&mut mapper, 0x2006, 0x00);
+    ppu.write_register(&mut mapper, 0x2006, 0x00);
+
+    ppu.mask = 0x1e;
+    ppu.oam = [0xff; 0x100];
+    ppu.oam[0..4].copy_from_slice(&[30, 0, 0, 100]);
+
+    while ppu.scanline < 240 {
+        ppu.step(&mut mapper);
+    }
+    assert_eq!(ppu.status & STATUS_SPRITE_ZERO, 0);
+}
+//}}}1
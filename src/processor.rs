@@ -2,16 +2,17 @@
 /* Standard Library Imports{{{2*/
 use std::{
     fmt,
-    fs::File,
-    io::{
-        BufReader,
-        prelude::*,
-    },
+    path::Path,
 };
 
 /* Crate Imports{{{2*/
 use crate::{
+    cartridge::{
+        Cartridge,
+        CartridgeError,
+    },
     memory::{
+        Bus,
         Readable,
         Writable,
     },
@@ -20,6 +21,7 @@ use crate::{
         MemAddressMode,
         Opcode,
         OPCODES,
+        CYCLES,
     },
     register::{
         Status,
@@ -30,20 +32,54 @@ use crate::{
 };
 //}}}1
 
+pub const NMI_VECTOR: u16 = 0xfffa;
+pub const RESET_VECTOR: u16 = 0xfffc;
+pub const IRQ_VECTOR: u16 = 0xfffe;
+
+const STACK_PAGE: u16 = 0x0100;
+const INTERRUPT_CYCLES: u64 = 7;
+const HIJACK_CYCLES: u32 = 4;       /* BRK and IRQ pick their vector after this many cycles */
+const BRK: u8 = 0x00;
+const OAM_DMA_CYCLES: u32 = 513;
+
 pub enum ProcState {
     Idle,
     Execute,
     MemoryRead,
     MemoryWrite,
+    Halted,
+}
+
+/* What to do with the opcodes whose results depend on analog effects in the chip */
+#[derive(Copy, Clone)]
+pub enum UnstableBehavior {
+    Emulate { magic: u8 },  /* Common behaviour; `magic` is the constant ANE and LXA OR into A */
+    Nop,                    /* Skip the instruction and its operand */
+    Jam,                    /* Halt like KIL */
+}
+
+/* The NES CPU is a 6502 with the decimal mode adjust removed; D can still be set and pushed */
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum CpuVariant {
+    Ricoh2A03,
+    Nmos6502,
 }
 
 pub struct Processor<T>
 where
-    T: Writable<u32> + Readable<u32> + Default
+    T: Writable<u16> + Readable<u16> + Default
 {
     pub registers: Registers,
     pub bus: T,
     pub state: ProcState,
+    pub cycles: u64,
+    pub unstable: UnstableBehavior,
+    pub variant: CpuVariant,
+    nmi_line: bool,
+    nmi_pending: bool,
+    nmi_at_vector: Option<bool>,    /* /NMI just before a BRK or IRQ vector fetch, when the caller knows it */
+    irq_line: bool,
+    delayed_interrupt_disable: Option<bool>,
 }
 
 impl fmt::Display for ProcState {
@@ -53,13 +89,14 @@ impl fmt::Display for ProcState {
             ProcState::Execute => "Executing",
             ProcState::MemoryRead => "Reading RAM",
             ProcState::MemoryWrite => "Writing RAM",
+            ProcState::Halted => "Halted",
         })
     }
 }
 
 impl<T> fmt::Display for Processor<T>
 where
-    T: Writable<u32> + Readable<u32> + Default
+    T: Writable<u16> + Readable<u16> + Default
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Processor:\n{}\n{}", self.registers, self.state)
@@ -68,128 +105,169 @@ where
 
 impl<T> Default for Processor<T>
 where
-    T: Readable<u32> + Writable<u32> + Default
+    T: Readable<u16> + Writable<u16> + Default
 {
     fn default() -> Self {
         Self::new()
     }
 }
 
+impl Processor<Bus> {
+    pub fn load_rom<P: AsRef<Path>>(&mut self, path: P) -> Result<(), CartridgeError> {
+        let cartridge = Cartridge::load(path)?;
+        self.bus.insert_cartridge(cartridge)
+    }
+
+    /* Runs one instruction and clocks the rest of the console by the cycles it took,
+     * then samples the interrupt lines it drives */
+    pub fn tick(&mut self) -> u32 {
+        /* An NMI raised during the first cycles of a BRK or IRQ sequence hijacks its vector,
+         * so the console is clocked up to the vector fetch first */
+        let early = if self.hijackable() { HIJACK_CYCLES } else { 0 };
+        if early > 0 {
+            self.bus.tick(early);
+            self.nmi_at_vector = Some(self.bus.ppu.nmi());
+        }
+
+        let mut cycles = self.step();
+
+        /* OAM DMA halts the CPU for 513 cycles, plus one to align when it starts on an odd cycle */
+        if let Some(page) = self.bus.oam_dma.take() {
+            let stall = OAM_DMA_CYCLES + (self.cycles & 1) as u32;
+            self.bus.run_oam_dma(page);
+            self.cycles += stall as u64;
+            cycles += stall;
+        }
+
+        self.bus.tick(cycles - early);
+
+        /* DMC fetches steal cycles, during which the rest of the console keeps running */
+        while self.bus.dmc_stall > 0 {
+            let stall = self.bus.dmc_stall;
+            self.bus.dmc_stall = 0;
+            self.cycles += stall as u64;
+            cycles += stall;
+            self.bus.tick(stall);
+        }
+
+        let nmi = self.bus.ppu.nmi();
+        let irq = self.bus.apu.irq() || self.bus.mapper.as_ref().is_some_and(|mapper| mapper.irq());
+        self.set_nmi(nmi);
+        self.set_irq(irq);
+
+        cycles
+    }
+
+    /* Whether the next step() starts a BRK or IRQ sequence */
+    fn hijackable(&self) -> bool {
+        if matches!(self.state, ProcState::Halted) || self.nmi_pending {
+            return false;
+        }
+
+        (self.irq_line && !self.interrupt_disable()) || self.bus.peek(self.registers.pc) == BRK
+    }
+}
+
 impl<T> Processor<T>
 where
-    T: Readable<u32> + Writable<u32> + Default
+    T: Readable<u16> + Writable<u16> + Default
 {
-    pub fn load_rom(&mut self) -> Result<(), String> {
-        let f = match File::open("roms/SMB.nes") {
-            Ok(f) => f,
-            Err(e) => return Err(format!("Error opening rom: {}", e)),
-        };
-        let mut reader = BufReader::new(f);
-        if let Err(e) = reader.read(self.bus.as_mut_slice(0x8000)) {
-            return Err(format!("Error reading rom: {}", e));
-        }
+    fn fetch_u8(&mut self) -> u8 {
+        let byte = self.bus.read_byte(self.registers.pc);
+        self.registers.pc = self.registers.pc.wrapping_add(1);
+        byte
+    }
 
-        Ok(())
+    fn fetch_u16(&mut self) -> u16 {
+        let low = self.fetch_u8() as u16;
+        let high = self.fetch_u8() as u16;
+        (high << 8) | low
     }
 
-    fn decode_augmented_u16(proc: &mut Processor<T>, mode: MemAddressMode) -> Option<u16> {
+    /* Reads a little-endian word without carrying into the high byte of the address, the
+     * way zero-page pointers and the NMOS JMP ($xxFF) fetch behave */
+    fn read_word_in_page(&mut self, addr: u16) -> u16 {
+        let high_addr = (addr & 0xff00) | (addr.wrapping_add(1) & 0x00ff);
+        let low = self.bus.read_byte(addr) as u16;
+        ((self.bus.read_byte(high_addr) as u16) << 8) | low
+    }
+
+    /* Resolves the operand address of a memory mode. `read` charges the page-crossing
+     * cycle that only read instructions pay. */
+    fn effective_address(&mut self, mode: MemAddressMode, read: bool) -> Option<u16> {
         match mode {
-            MemAddressMode::Absolute => {
-                proc.registers.pc += 2_u16;
-                Some(proc.bus.read_word(u32::from(proc.registers.pc) - 2))
-            },
-            MemAddressMode::AbsoluteIndexedX => {
-                proc.registers.pc += 2_u16;
-                Some(proc.bus.read_word(u32::from(proc.registers.pc) - 2)
-                    .wrapping_add(proc.registers.x as u16))
-            },
-            MemAddressMode::AbsoluteIndexedY => {
-                proc.registers.pc += 2_u16;
-                Some(proc.bus.read_word(u32::from(proc.registers.pc) - 2)
-                    .wrapping_add(proc.registers.y as u16))
+            MemAddressMode::Absolute => Some(self.fetch_u16()),
+            MemAddressMode::AbsoluteIndexedX | MemAddressMode::AbsoluteIndexedY => {
+                let index = if let MemAddressMode::AbsoluteIndexedX = mode { self.registers.x } else { self.registers.y };
+                let base = self.fetch_u16();
+                Some(if read { self.indexed_read_address(base, index) } else { base.wrapping_add(index as u16) })
             },
             MemAddressMode::Indirect => {
-                proc.registers.pc += 2_u16;
-                Some(proc.bus.read_word(proc.bus.read_word(u32::from(proc.registers.pc) - 2) as u32))
+                let pointer = self.fetch_u16();
+                Some(self.read_word_in_page(pointer))
             },
             MemAddressMode::IndirectIndexedX => {
-                proc.registers.pc += 1_u16;
-                let addr = proc.registers.pc - 2;
-                let addr = addr.saturating_add(proc.registers.x as u16);
-                Some(proc.bus.read_word(proc.bus.read_byte(addr as u32) as u32))
+                let pointer = self.fetch_u8().wrapping_add(self.registers.x);
+                Some(self.read_word_in_page(pointer as u16))
             },
             MemAddressMode::IndirectIndexedY => {
-                proc.registers.pc += 1_u16;
-                let addr = proc.registers.pc - 2;
-                Some(proc.bus.read_word((proc.bus.read_byte(addr as u32)).wrapping_add(proc.registers.y) as u32))
-            },
-            MemAddressMode::ZeroPage => {
-                proc.registers.pc += 1_u16;
-                Some(proc.bus.read_byte(u32::from(proc.registers.pc) - 1) as u16)
+                let pointer = self.fetch_u8();
+                let base = self.read_word_in_page(pointer as u16);
+                Some(if read { self.indexed_read_address(base, self.registers.y) } else { base.wrapping_add(self.registers.y as u16) })
             },
-            MemAddressMode::ZeroPageIndexedX => {
-                proc.registers.pc += 1_u16;
-                Some(proc.bus.read_byte(u32::from(proc.registers.pc) - 1).saturating_add(proc.registers.x) as u16)
+            MemAddressMode::ZeroPage => Some(self.fetch_u8() as u16),
+            MemAddressMode::ZeroPageIndexedX => Some(self.fetch_u8().wrapping_add(self.registers.x) as u16),
+            MemAddressMode::ZeroPageIndexedY => Some(self.fetch_u8().wrapping_add(self.registers.y) as u16),
+            _ => None,
+        }
+    }
+
+    /* Read-modify-write instructions write the unmodified value back while the ALU works,
+     * then write the result; registers with write side effects see both. Accumulator
+     * forms operate on A. Returns the value written. */
+    fn read_modify_write<F>(&mut self, mode: MemAddressMode, modify: F) -> u8
+    where
+        F: FnOnce(&mut StatusRegister, &mut u8)
+    {
+        match Processor::decode_augmented_u16(self, mode) {
+            Some(addr) => {
+                let mut val = self.bus.read_byte(addr);
+                self.bus.write_byte(addr, val);
+                modify(&mut self.registers.sr, &mut val);
+                self.bus.write_byte(addr, val);
+                val
             },
-            MemAddressMode::ZeroPageIndexedY => {
-                proc.registers.pc += 1_u16;
-                Some(proc.bus.read_byte(u32::from(proc.registers.pc) - 1).saturating_add(proc.registers.y) as u16)
+            None => {
+                modify(&mut self.registers.sr, &mut self.registers.a);
+                self.registers.a
             },
-            _ => None,
         }
     }
 
+    fn decode_augmented_u16(proc: &mut Processor<T>, mode: MemAddressMode) -> Option<u16> {
+        proc.effective_address(mode, false)
+    }
+
     fn decode_augmented_u8(proc: &mut Processor<T>, mode: MemAddressMode) -> Option<u8> {
         match mode {
             MemAddressMode::Implied | MemAddressMode::Accumulator => None,
-            MemAddressMode::Relative | MemAddressMode::Immediate => {
-                proc.registers.pc += 1_u16;
-                Some(proc.bus.read_byte(u32::from(proc.registers.pc) - 1))
-            },
-            MemAddressMode::Absolute => {
-                proc.registers.pc += 2_u16;
-                Some(proc.bus.read_byte(proc.bus.read_word(u32::from(proc.registers.pc) - 2) as u32))
+            MemAddressMode::Relative | MemAddressMode::Immediate => Some(proc.fetch_u8()),
+            _ => {
+                let addr = proc.effective_address(mode, true)?;
+                Some(proc.bus.read_byte(addr))
             },
-            MemAddressMode::AbsoluteIndexedX => {
-                proc.registers.pc += 2_u16;
-                Some(proc.bus.read_byte(proc.bus.read_word(u32::from(proc.registers.pc) - 2).wrapping_add(proc.registers.x as u16) as u32))
-            },
-            MemAddressMode::AbsoluteIndexedY => {
-                proc.registers.pc += 2_u16;
-                Some(proc.bus.read_byte(proc.bus.read_word(u32::from(proc.registers.pc) - 2).wrapping_add(proc.registers.y as u16) as u32))
-            },
-            MemAddressMode::Indirect => {
-                proc.registers.pc += 2_u16;
-                Some(proc.bus.read_byte(proc.bus.read_word(proc.bus.read_word(u32::from(proc.registers.pc) - 2) as u32) as u32))
-            },
-            MemAddressMode::IndirectIndexedX => {
-                proc.registers.pc += 1_u16;
-                Some(proc.bus.read_byte(
-                        proc.bus.read_word(
-
-                        proc.bus.read_byte((u32::from(proc.registers.pc) - 1).saturating_add(proc.registers.x as u32) as u32)
+        }
+    }
 
-                        as u32) as u32))
-            },
-            MemAddressMode::IndirectIndexedY => {
-                proc.registers.pc += 1_u16;
-                Some(proc.bus.read_byte(
-                        proc.bus.read_word(proc.bus.read_byte(u32::from(proc.registers.pc) - 1) as u32)
-                        .wrapping_add(proc.registers.y as u16) as u32))
-            },
-            MemAddressMode::ZeroPage => {
-                proc.registers.pc += 1_u16;
-                Some(proc.bus.read_byte(proc.bus.read_byte(u32::from(proc.registers.pc) - 1) as u32))
-            },
-            MemAddressMode::ZeroPageIndexedX => {
-                proc.registers.pc += 1_u16;
-                Some(proc.bus.read_byte((proc.registers.x as u32).saturating_add(proc.bus.read_byte(u32::from(proc.registers.pc) - 1) as u32)))
-            },
-            MemAddressMode::ZeroPageIndexedY => {
-                proc.registers.pc += 1_u16;
-                Some(proc.bus.read_byte((proc.registers.y as u32).saturating_add(proc.bus.read_byte(u32::from(proc.registers.pc) - 1) as u32)))
-            },
+    /* Indexed reads take an extra cycle to fix up the high byte when the index carries
+     * into the next page. Writes and read-modify-writes always pay it in their base count. */
+    fn indexed_read_address(&mut self, base: u16, index: u8) -> u16 {
+        let addr = base.wrapping_add(index as u16);
+        if (addr & 0xff00) != (base & 0xff00) {
+            self.cycles += 1;
         }
+
+        addr
     }
 
     pub fn new() -> Processor<T> {
@@ -197,9 +275,102 @@ where
             registers: Registers::new(),
             bus: T::default(),
             state: ProcState::Idle,
+            cycles: 0,
+            unstable: UnstableBehavior::Emulate { magic: 0xee },
+            variant: CpuVariant::Ricoh2A03,
+            nmi_line: false,
+            nmi_pending: false,
+            nmi_at_vector: None,
+            irq_line: false,
+            delayed_interrupt_disable: None,
         }
     }
 
+    pub fn power_on(&mut self) {
+        self.registers = Registers::new();
+        self.cycles = 0;
+        self.reset();
+    }
+
+    /* RESET runs the interrupt sequence with writes suppressed: SP still drops by three */
+    pub fn reset(&mut self) {
+        self.registers.sp = self.registers.sp.wrapping_sub(3);
+        self.registers.sr.set_flag(Status::InterruptDisable);
+        self.registers.pc = self.bus.read_word(RESET_VECTOR);
+        self.nmi_pending = false;
+        self.nmi_at_vector = None;
+        self.delayed_interrupt_disable = None;
+        self.state = ProcState::Idle;
+        self.cycles += INTERRUPT_CYCLES;
+    }
+
+    /* NMI is edge-triggered: only a high-to-low transition of /NMI (true = asserted) latches one */
+    pub fn set_nmi(&mut self, asserted: bool) {
+        if asserted && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = asserted;
+    }
+
+    /* IRQ is level-triggered and is serviced for as long as the line is held and I is clear */
+    pub fn set_irq(&mut self, asserted: bool) {
+        self.irq_line = asserted;
+    }
+
+    /* Pushes PC and status, then jumps through `vector`. An NMI that arrives while a BRK or
+     * IRQ is being serviced hijacks the vector fetch, but the pushed B flag still tells
+     * which of the two started the sequence. */
+    fn interrupt(&mut self, vector: u16, return_addr: u16, break_flag: bool) {
+        self.push((return_addr >> 8) as u8);
+        self.push(return_addr as u8);
+
+        let mut status = self.registers.sr;
+        status.set_flag(Status::Mix(1 << 5));
+        if break_flag {
+            status.set_flag(Status::Break);
+        }
+        else {
+            status.clear_flag(Status::Break);
+        }
+        self.push(status.0);
+
+        if let Some(asserted) = self.nmi_at_vector.take() {
+            self.set_nmi(asserted);
+        }
+        let vector = if vector != NMI_VECTOR && self.nmi_pending {
+            self.nmi_pending = false;
+            NMI_VECTOR
+        }
+        else {
+            vector
+        };
+
+        self.registers.sr.set_flag(Status::InterruptDisable);
+        self.registers.pc = self.bus.read_word(vector);
+    }
+
+    /* The stack lives on page $01 and SP wraps within it in both directions */
+    fn push(&mut self, byte: u8) {
+        self.bus.write_byte(STACK_PAGE | self.registers.sp as u16, byte);
+        self.registers.sp = self.registers.sp.wrapping_sub(1);
+    }
+
+    fn pull(&mut self) -> u8 {
+        self.registers.sp = self.registers.sp.wrapping_add(1);
+        self.bus.read_byte(STACK_PAGE | self.registers.sp as u16)
+    }
+
+    /* CLI, SEI and PLP change I after the interrupt lines have been polled, so the
+     * next poll still sees the old value */
+    fn delay_interrupt_disable(&mut self) {
+        self.delayed_interrupt_disable = Some(self.registers.sr.contains(Status::InterruptDisable));
+    }
+
+    /* I as the next interrupt poll sees it */
+    fn interrupt_disable(&self) -> bool {
+        self.delayed_interrupt_disable.unwrap_or_else(|| self.registers.sr.contains(Status::InterruptDisable))
+    }
+
     pub fn execute_instruction(&mut self, inst: Instruction) { //{{{1
         let op = inst.0;
 
@@ -215,25 +386,21 @@ where
                 }
             },
             Opcode::ASL => {
-                let target = match Processor::decode_augmented_u16(self, inst.1) {
-                    Some(addr) => &mut self.bus.as_mut_slice(addr as u32)[0],
-                    None => &mut self.registers.a,
-                };
-                cpu::shift_left(&mut self.registers.sr, target);
+                self.read_modify_write(inst.1, cpu::shift_left);
             },
             Opcode::BCC => {
                 if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
-                    if !self.registers.sr.contains(Status::Carry) { cpu::branch(&mut self.registers.pc, val); }
+                    if !self.registers.sr.contains(Status::Carry) { self.cycles += cpu::branch(&mut self.registers.pc, val) as u64; }
                 }
             },
             Opcode::BCS => {
                 if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
-                    if self.registers.sr.contains(Status::Carry) { cpu::branch(&mut self.registers.pc, val); }
+                    if self.registers.sr.contains(Status::Carry) { self.cycles += cpu::branch(&mut self.registers.pc, val) as u64; }
                 }
             },
             Opcode::BEQ => {
                 if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
-                    if self.registers.sr.contains(Status::Zero) { cpu::branch(&mut self.registers.pc, val); }
+                    if self.registers.sr.contains(Status::Zero) { self.cycles += cpu::branch(&mut self.registers.pc, val) as u64; }
                 }
             },
             Opcode::BIT => {
@@ -243,38 +410,36 @@ where
             },
             Opcode::BMI => {
                 if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
-                    if self.registers.sr.contains(Status::Negative) { cpu::branch(&mut self.registers.pc, val); }
+                    if self.registers.sr.contains(Status::Negative) { self.cycles += cpu::branch(&mut self.registers.pc, val) as u64; }
                 }
             },
             Opcode::BNE => {
                 if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
-                    if !self.registers.sr.contains(Status::Zero) { cpu::branch(&mut self.registers.pc, val); }
+                    if !self.registers.sr.contains(Status::Zero) { self.cycles += cpu::branch(&mut self.registers.pc, val) as u64; }
                 }
             },
             Opcode::BPL => {
                 if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
-                    if !self.registers.sr.contains(Status::Negative) { cpu::branch(&mut self.registers.pc, val); }
+                    if !self.registers.sr.contains(Status::Negative) { self.cycles += cpu::branch(&mut self.registers.pc, val) as u64; }
                 }
             },
-            Opcode::BRK => {
-                self.registers.sr.set_flag(Status::InterruptDisable);
-                self.bus.write_word(self.registers.sp as u32, self.registers.pc + 2);
-                self.bus.write_byte(self.registers.sp as u32 + 2, u8::from(self.registers.sr));
-                self.registers.sp += 3;
-            },
+            Opcode::BRK => self.force_break(),
             Opcode::BVC => {
                 if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
-                    if !self.registers.sr.contains(Status::Overflow) { cpu::branch(&mut self.registers.pc, val); }
+                    if !self.registers.sr.contains(Status::Overflow) { self.cycles += cpu::branch(&mut self.registers.pc, val) as u64; }
                 }
             },
             Opcode::BVS => {
                 if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
-                    if self.registers.sr.contains(Status::Overflow) { cpu::branch(&mut self.registers.pc, val); }
+                    if self.registers.sr.contains(Status::Overflow) { self.cycles += cpu::branch(&mut self.registers.pc, val) as u64; }
                 }
             },
             Opcode::CLC => self.registers.sr.clear_flag(Status::Carry),
             Opcode::CLD => self.registers.sr.clear_flag(Status::Decimal),
-            Opcode::CLI => self.registers.sr.clear_flag(Status::InterruptDisable),
+            Opcode::CLI => {
+                self.delay_interrupt_disable();
+                self.registers.sr.clear_flag(Status::InterruptDisable);
+            },
             Opcode::CLV => self.registers.sr.clear_flag(Status::Overflow),
             Opcode::CMP => {
                 if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
@@ -292,10 +457,10 @@ where
                 }
             },
             Opcode::DEC => {
-                if let Some(addr) = Processor::decode_augmented_u16(self, inst.1) {
-                    let target = &mut self.bus.as_mut_slice(addr as u32)[0];
-                    cpu::load_u8_memory(&mut self.registers.sr, target, *target - 1);
-                }
+                self.read_modify_write(inst.1, |sr, target| {
+                    let val = target.wrapping_sub(1);
+                    cpu::load_u8_memory(sr, target, val);
+                });
             },
             Opcode::DEX => self.decrement_x(),
             Opcode::DEY => self.decrement_y(),
@@ -305,10 +470,10 @@ where
                 }
             },
             Opcode::INC => {
-                if let Some(addr) = Processor::decode_augmented_u16(self, inst.1) {
-                    let target = &mut self.bus.as_mut_slice(addr as u32)[0];
-                    cpu::load_u8_memory(&mut self.registers.sr, target, *target - 1);
-                }
+                self.read_modify_write(inst.1, |sr, target| {
+                    let val = target.wrapping_add(1);
+                    cpu::load_u8_memory(sr, target, val);
+                });
             },
             Opcode::INX => self.increment_x(),
             Opcode::INY => self.increment_y(),
@@ -338,13 +503,12 @@ where
                 }
             },
             Opcode::LSR => {
-                let target = match Processor::decode_augmented_u16(self, inst.1) {
-                    Some(addr) => &mut self.bus.as_mut_slice(addr as u32)[0],
-                    None => &mut self.registers.a,
-                };
-                cpu::shift_right(&mut self.registers.sr, target);
+                self.read_modify_write(inst.1, cpu::shift_right);
+            },
+            Opcode::NOP => {
+                /* The unofficial variants still fetch, and read, their operand */
+                Processor::decode_augmented_u8(self, inst.1);
             },
-            Opcode::NOP => {},
             Opcode::ORA => {
                 if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
                     self.or(val);
@@ -353,30 +517,18 @@ where
             Opcode::PHA => self.push_accumulator(),
             Opcode::PHP => self.push_status(),
             Opcode::PLA => self.pop_accumulator(),
-            Opcode::PLP => self.pop_status(),
+            Opcode::PLP => {
+                self.delay_interrupt_disable();
+                self.pop_status();
+            },
             Opcode::ROL => {
-                let target = match Processor::decode_augmented_u16(self, inst.1) {
-                    Some(addr) => &mut self.bus.as_mut_slice(addr as u32)[0],
-                    None => &mut self.registers.a,
-                };
-                cpu::rotate_left(&mut self.registers.sr, target);
+                self.read_modify_write(inst.1, cpu::rotate_left);
             },
             Opcode::ROR => {
-                let target = match Processor::decode_augmented_u16(self, inst.1) {
-                    Some(addr) => &mut self.bus.as_mut_slice(addr as u32)[0],
-                    None => &mut self.registers.a,
-                };
-                cpu::rotate_right(&mut self.registers.sr, target);
-            },
-            Opcode::RTI => {
-                self.registers.sr = StatusRegister(self.bus.read_byte(self.registers.sp as u32));
-                self.registers.pc = self.bus.read_word(self.registers.sp as u32 + 1);
-                self.registers.sp += 3;
-            },
-            Opcode::RTS => {
-                self.registers.pc = self.bus.read_word(self.registers.sp as u32);
-                self.registers.sp += 2;
+                self.read_modify_write(inst.1, cpu::rotate_right);
             },
+            Opcode::RTI => self.return_from_interrupt(),
+            Opcode::RTS => self.return_from_subroutine(),
             Opcode::SBC => {
                 if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
                     self.subtract_with_carry(val);
@@ -384,75 +536,288 @@ where
             },
             Opcode::SEC => self.registers.sr.set_flag(Status::Carry),
             Opcode::SED => self.registers.sr.set_flag(Status::Decimal),
-            Opcode::SEI => self.registers.sr.set_flag(Status::InterruptDisable),
+            Opcode::SEI => {
+                self.delay_interrupt_disable();
+                self.registers.sr.set_flag(Status::InterruptDisable);
+            },
             Opcode::STA => {
                 if let Some(addr) = Processor::decode_augmented_u16(self, inst.1) {
-                    self.bus.write_byte(addr as u32, self.registers.a);
+                    self.bus.write_byte(addr, self.registers.a);
                 }
             },
             Opcode::STX => {
                 if let Some(addr) = Processor::decode_augmented_u16(self, inst.1) {
-                    self.bus.write_byte(addr as u32, self.registers.x);
+                    self.bus.write_byte(addr, self.registers.x);
                 }
             },
             Opcode::STY => {
                 if let Some(addr) = Processor::decode_augmented_u16(self, inst.1) {
-                    self.bus.write_byte(addr as u32, self.registers.y);
+                    self.bus.write_byte(addr, self.registers.y);
                 }
             },
-            Opcode::TAX => self.registers.x = self.registers.a,
-            Opcode::TAY => self.registers.y = self.registers.a,
+            Opcode::TAX => cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.x, self.registers.a),
+            Opcode::TAY => cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.y, self.registers.a),
             Opcode::TSX => cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.x, self.registers.sp),
-            Opcode::TXA => self.registers.a = self.registers.x,
-            Opcode::TXS => self.registers.sr = StatusRegister(self.registers.x),
-            Opcode::TYA => self.registers.a = self.registers.y,
+            Opcode::TXA => cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a, self.registers.x),
+            Opcode::TXS => self.registers.sp = self.registers.x,    /* The only transfer that leaves the flags alone */
+            Opcode::TYA => cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a, self.registers.y),
+
+            /* Unofficial opcodes {{{2 */
+            Opcode::ALR => {
+                if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
+                    self.and(val);
+                    cpu::shift_right(&mut self.registers.sr, &mut self.registers.a);
+                }
+            },
+            Opcode::ANC => {
+                if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
+                    self.and(val);
+                    self.copy_flag(Status::Carry, self.registers.a & 0x80 != 0);
+                }
+            },
+            Opcode::ANE => {
+                if let Some(magic) = self.unstable_magic(inst.1) {
+                    if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
+                        let val = (self.registers.a | magic) & self.registers.x & val;
+                        cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a, val);
+                    }
+                }
+            },
+            Opcode::ARR => {
+                if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
+                    let carry = self.registers.sr.flag_value(Status::Carry) << 7;
+                    let val = ((self.registers.a & val) >> 1) | carry;
+                    cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a, val);
+                    self.copy_flag(Status::Carry, val & 0x40 != 0);
+                    self.copy_flag(Status::Overflow, ((val >> 6) ^ (val >> 5)) & 1 != 0);
+                }
+            },
+            Opcode::AXS => {
+                if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
+                    let masked = self.registers.a & self.registers.x;
+                    cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.x, masked.wrapping_sub(val));
+                    self.copy_flag(Status::Carry, masked >= val);
+                }
+            },
+            Opcode::DCP => {
+                let val = self.read_modify_write(inst.1, |_, target| *target = target.wrapping_sub(1));
+                self.compare_with_accumulator(val);
+            },
+            Opcode::ISC => {
+                let val = self.read_modify_write(inst.1, |_, target| *target = target.wrapping_add(1));
+                self.subtract_with_carry(val);
+            },
+            Opcode::JAM => self.jam(),
+            Opcode::LAS => {
+                if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
+                    let val = val & self.registers.sp;
+                    cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a, val);
+                    self.registers.x = val;
+                    self.registers.sp = val;
+                }
+            },
+            Opcode::LAX => {
+                if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
+                    cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a, val);
+                    self.registers.x = val;
+                }
+            },
+            Opcode::LXA => {
+                if let Some(magic) = self.unstable_magic(inst.1) {
+                    if let Some(val) = Processor::decode_augmented_u8(self, inst.1) {
+                        let val = (self.registers.a | magic) & val;
+                        cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a, val);
+                        self.registers.x = val;
+                    }
+                }
+            },
+            Opcode::RLA => {
+                let val = self.read_modify_write(inst.1, cpu::rotate_left);
+                self.and(val);
+            },
+            Opcode::RRA => {
+                let val = self.read_modify_write(inst.1, cpu::rotate_right);
+                self.add_with_carry(val);
+            },
+            Opcode::SAX => {
+                if let Some(addr) = Processor::decode_augmented_u16(self, inst.1) {
+                    self.bus.write_byte(addr, self.registers.a & self.registers.x);
+                }
+            },
+            Opcode::SHA => {
+                if self.unstable_magic(inst.1).is_some() {
+                    self.store_high_and(inst.1, self.registers.a & self.registers.x);
+                }
+            },
+            Opcode::SHX => {
+                if self.unstable_magic(inst.1).is_some() {
+                    self.store_high_and(inst.1, self.registers.x);
+                }
+            },
+            Opcode::SHY => {
+                if self.unstable_magic(inst.1).is_some() {
+                    self.store_high_and(inst.1, self.registers.y);
+                }
+            },
+            Opcode::SLO => {
+                let val = self.read_modify_write(inst.1, cpu::shift_left);
+                self.or(val);
+            },
+            Opcode::SRE => {
+                let val = self.read_modify_write(inst.1, cpu::shift_right);
+                self.xor_accumulator(val);
+            },
+            Opcode::TAS => {
+                if self.unstable_magic(inst.1).is_some() {
+                    self.registers.sp = self.registers.a & self.registers.x;
+                    self.store_high_and(inst.1, self.registers.sp);
+                }
+            },
         };
     }//}}}1
 
+    /* KIL/JAM locks the CPU up until RESET; PC is left on the offending opcode */
+    fn jam(&mut self) {
+        self.state = ProcState::Halted;
+        self.registers.pc = self.registers.pc.wrapping_sub(1);
+    }
+
+    /* Gate for the unstable opcodes: returns the magic constant when they should run,
+     * otherwise skips or jams as configured */
+    fn unstable_magic(&mut self, mode: MemAddressMode) -> Option<u8> {
+        match self.unstable {
+            UnstableBehavior::Emulate { magic } => Some(magic),
+            UnstableBehavior::Nop => {
+                self.registers.pc = self.registers.pc.wrapping_add(mode.operand_len());
+                None
+            },
+            UnstableBehavior::Jam => {
+                self.jam();
+                None
+            },
+        }
+    }
+
+    /* SHA, SHX, SHY and TAS AND the stored value with the high byte of the base address
+     * plus one. When indexing crosses a page, that value also replaces the target's high byte. */
+    fn store_high_and(&mut self, mode: MemAddressMode, val: u8) {
+        let (base, index) = match mode {
+            MemAddressMode::AbsoluteIndexedX | MemAddressMode::AbsoluteIndexedY => {
+                self.registers.pc = self.registers.pc.wrapping_add(2);
+                let base = self.bus.read_word(self.registers.pc.wrapping_sub(2));
+                let index = if let MemAddressMode::AbsoluteIndexedX = mode { self.registers.x } else { self.registers.y };
+                (base, index)
+            },
+            _ => {
+                self.registers.pc = self.registers.pc.wrapping_add(1);
+                let pointer = self.bus.read_byte(self.registers.pc.wrapping_sub(1));
+                let low = self.bus.read_byte(pointer as u16) as u16;
+                let high = self.bus.read_byte(pointer.wrapping_add(1) as u16) as u16;
+                ((high << 8) | low, self.registers.y)
+            },
+        };
+
+        let addr = base.wrapping_add(index as u16);
+        let val = val & ((base >> 8) as u8).wrapping_add(1);
+        let addr = if (addr & 0xff00) != (base & 0xff00) { ((val as u16) << 8) | (addr & 0x00ff) } else { addr };
+
+        self.bus.write_byte(addr, val);
+    }
+
+    fn copy_flag(&mut self, flag: Status, set: bool) {
+        if set {
+            self.registers.sr.set_flag(flag);
+        }
+        else {
+            self.registers.sr.clear_flag(flag);
+        }
+    }
+
     pub fn read_next_instruction(&mut self) -> Option<Instruction> {
-        let op = self.bus.read_byte(u32::from(self.registers.pc));
-        self.registers.pc += 1_u16;
+        let op = self.fetch_u8();
+        self.cycles += CYCLES[op as usize] as u64;
 
         OPCODES[op as usize]
     }
 
-    pub fn add_with_carry(&mut self, val: u8) {
-        let mut set = Status::Mix(0);
-        let mut clear = Status::Zero | Status::Negative | Status::Overflow;
-
-        let accumulator = self.registers.a;
-        let val = val + self.registers.sr.flag_value(Status::Carry);
+    /* Runs one instruction and returns how many CPU cycles it took, so the caller can
+     * clock the PPU and APU by the same amount. */
+    pub fn step(&mut self) -> u32 {
+        /* A jammed CPU stops fetching, but the rest of the console keeps running */
+        if matches!(self.state, ProcState::Halted) {
+            self.cycles += 1;
+            return 1;
+        }
 
-        cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a, accumulator + val);
+        let start = self.cycles;
+        let interrupt_disable = self.interrupt_disable();
+        self.delayed_interrupt_disable = None;
 
-        if self.registers.sr.contains(Status::Decimal) {
-            let mut nibble_low = self.registers.a & 0xf;
-            let mut nibble_high = self.registers.a >> 4;
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            self.interrupt(NMI_VECTOR, self.registers.pc, false);
+            self.cycles += INTERRUPT_CYCLES;
+        }
+        else if self.irq_line && !interrupt_disable {
+            self.interrupt(IRQ_VECTOR, self.registers.pc, false);
+            self.cycles += INTERRUPT_CYCLES;
+        }
+        else if let Some(inst) = self.read_next_instruction() {
+            self.execute_instruction(inst);
+        }
 
-            if nibble_low > 9 {
-                nibble_low -= 10;
-                nibble_high += 1;
-            }
-            if nibble_high > 9 {
-                nibble_high -= 10;
-            }
+        (self.cycles - start) as u32
+    }
 
-            self.registers.a = (nibble_high << 4) | nibble_low;
+    pub fn add_with_carry(&mut self, val: u8) {
+        if self.decimal_mode() {
+            self.add_with_carry_decimal(val);
+        }
+        else {
+            self.add_with_carry_binary(val);
         }
+    }
 
-        /* Flag Checks */
-        if self.registers.a < accumulator || self.registers.a < val {
-            set |= Status::Carry;
-            clear ^= Status::Carry;
+    /* The 2A03 has the D flag but its ALU has no decimal adjust circuitry */
+    fn decimal_mode(&self) -> bool {
+        match self.variant {
+            CpuVariant::Ricoh2A03 => false,
+            CpuVariant::Nmos6502 => self.registers.sr.contains(Status::Decimal),
         }
+    }
 
-        if self.registers.a & 0x80 != ((accumulator & 0x80) | (val & 0x80)) {
-            set |= Status::Overflow;
-            clear ^= Status::Overflow;
+    fn add_with_carry_binary(&mut self, val: u8) {
+        let accumulator = self.registers.a;
+        let sum = accumulator as u16 + val as u16 + self.registers.sr.flag_value(Status::Carry) as u16;
+        let result = sum as u8;
+
+        cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a, result);
+        self.copy_flag(Status::Carry, sum > 0xff);
+        self.copy_flag(Status::Overflow, (!(accumulator ^ val) & (accumulator ^ result) & 0x80) != 0);
+    }
+
+    /* NMOS decimal mode: Z comes from the binary sum, N and V from the sum after the
+     * low nibble is adjusted but before the high one is, and C from the final BCD result */
+    fn add_with_carry_decimal(&mut self, val: u8) {
+        let accumulator = self.registers.a as u16;
+        let val = val as u16;
+        let carry = self.registers.sr.flag_value(Status::Carry) as u16;
+
+        let mut sum = (accumulator & 0x0f) + (val & 0x0f) + carry;
+        if sum > 0x09 {
+            sum += 0x06;
         }
+        sum = (sum & 0x0f) + (accumulator & 0xf0) + (val & 0xf0) + if sum > 0x0f { 0x10 } else { 0 };
 
-        self.registers.sr.set_flag(set);
-        self.registers.sr.clear_flag(clear);
+        self.copy_flag(Status::Zero, (accumulator + val + carry) & 0xff == 0);
+        self.copy_flag(Status::Negative, sum & 0x80 != 0);
+        self.copy_flag(Status::Overflow, ((accumulator ^ sum) & 0x80) != 0 && ((accumulator ^ val) & 0x80) == 0);
+
+        if (sum & 0x1f0) > 0x90 {
+            sum += 0x60;
+        }
+        self.copy_flag(Status::Carry, (sum & 0xff0) > 0xf0);
+        self.registers.a = sum as u8;
     }
 
     pub fn and(&mut self, val: u8) {
@@ -484,10 +849,10 @@ where
         self.registers.sr.set_flag(set);
     }
 
+    /* BRK is two bytes long; the byte after the opcode is skipped on return */
     pub fn force_break(&mut self) {
-        self.registers.sr.set_flag(Status::InterruptDisable);
-        self.bus.write_word(self.registers.sp as u32 - 2, self.registers.pc + 2);
-        self.bus.write_byte(self.registers.sp as u32 - 3, self.registers.sr.0);
+        let return_addr = self.registers.pc.wrapping_add(1);
+        self.interrupt(IRQ_VECTOR, return_addr, true);
     }
 
     pub fn compare_with_accumulator(&mut self, val: u8) {
@@ -503,29 +868,17 @@ where
     }
 
     pub fn decrement(&mut self, val: &mut u8) {
-        cpu::load_u8_memory(&mut self.registers.sr, val, *val - 1);
-
-        if self.registers.sr.contains(Status::Decimal) {
-            *val = cpu::format_bcd(*val);
-        }
+        cpu::load_u8_memory(&mut self.registers.sr, val, val.wrapping_sub(1));
     }
 
     pub fn decrement_x(&mut self) {
-        let val = self.registers.x - 1;
+        let val = self.registers.x.wrapping_sub(1);
         cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.x, val);
-
-        if self.registers.sr.contains(Status::Decimal) {
-            self.registers.x = cpu::format_bcd(self.registers.x);
-        }
     }
 
     pub fn decrement_y(&mut self) {
-        let val = self.registers.y - 1;
+        let val = self.registers.y.wrapping_sub(1);
         cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.y, val);
-
-        if self.registers.sr.contains(Status::Decimal) {
-            self.registers.y = cpu::format_bcd(self.registers.y);
-        }
     }
 
     pub fn xor_accumulator(&mut self, val: u8) {
@@ -534,38 +887,28 @@ where
     }
 
     pub fn increment(&mut self, val: &mut u8) {
-        cpu::load_u8_memory(&mut self.registers.sr, val, *val + 1);
-
-        if self.registers.sr.contains(Status::Decimal) {
-            *val = cpu::format_bcd(*val);
-        }
-   }
+        cpu::load_u8_memory(&mut self.registers.sr, val, val.wrapping_add(1));
+    }
 
     pub fn increment_x(&mut self) {
-        let val = self.registers.x + 1;
+        let val = self.registers.x.wrapping_add(1);
         cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.x, val);
-
-        if self.registers.sr.contains(Status::Decimal) {
-            self.registers.x = cpu::format_bcd(self.registers.x);
-        }
     }
 
     pub fn increment_y(&mut self) {
-        let val = self.registers.y + 1;
+        let val = self.registers.y.wrapping_add(1);
         cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.y, val);
-
-        if self.registers.sr.contains(Status::Decimal) {
-            self.registers.y = cpu::format_bcd(self.registers.y);
-        }
     }
 
     pub fn jump(&mut self, addr: u16) {
         self.registers.pc = addr;
     }
 
+    /* JSR pushes the address of its own last byte, high byte first; RTS adds the one back */
     pub fn jump_save_return(&mut self, addr: u16) {
-        self.registers.sp -= 2;
-        self.bus.write_word(self.registers.sp as u32, self.registers.pc);
+        let return_addr = self.registers.pc.wrapping_sub(1);
+        self.push((return_addr >> 8) as u8);
+        self.push(return_addr as u8);
         self.registers.pc = addr;
     }
 
@@ -575,110 +918,93 @@ where
     }
 
     pub fn push_accumulator(&mut self) {
-        self.registers.sp -= 1;
-        self.bus.write_byte(self.registers.sp as u32, self.registers.a);
+        self.push(self.registers.a);
     }
 
+    /* B only exists on the stack: PHP pushes it set, along with the unused bit */
     pub fn push_status(&mut self) {
-        self.registers.sp -= 1;
-        self.registers.sr.set_flag(Status::Break | Status::Mix(1 << 5));
-        self.bus.write_byte(self.registers.sp as u32, self.registers.sr.0);
+        let mut status = self.registers.sr;
+        status.set_flag(Status::Break | Status::Mix(1 << 5));
+        self.push(status.0);
     }
 
     pub fn pop_accumulator(&mut self) {
-        cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a,
-            self.bus.read_byte(self.registers.sp as u32)
-        );
-        self.registers.sp += 1;
+        let val = self.pull();
+        cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a, val);
     }
 
     pub fn pop_status(&mut self) {
-        self.registers.sr = StatusRegister(self.bus.read_byte(self.registers.sp as u32));
-        self.registers.sp += 1;
-        self.registers.sr.clear_flag(Status::Mix(1 << 5));
+        let status = self.pull();
+        self.registers.sr = Processor::<T>::status_from_stack(status);
     }
 
-    pub fn rotate_left(&mut self, val: &mut u8) {
-        if *val & 0x80 == 0x80 {
-            self.registers.sr.set_flag(Status::Carry);
-        }
-        else {
-            self.registers.sr.clear_flag(Status::Carry);
-        }
+    /* The B and unused bits are not latched from the stack: B reads back clear, unused set */
+    fn status_from_stack(byte: u8) -> StatusRegister {
+        let mut status = StatusRegister(byte);
+        status.clear_flag(Status::Break);
+        status.set_flag(Status::Mix(1 << 5));
+        status
+    }
 
-        let rot_val = (*val << 1) | (*val >> 7);
-        cpu::load_u8_memory(&mut self.registers.sr, val, rot_val);
+    /* ROL and ROR shift through the carry rather than rotating the byte on itself */
+    pub fn rotate_left(&mut self, val: &mut u8) {
+        cpu::rotate_left(&mut self.registers.sr, val);
     }
 
     pub fn rotate_right(&mut self, val: &mut u8) {
-        if *val & 1 == 1 {
-            self.registers.sr.set_flag(Status::Carry);
-        }
-        else {
-            self.registers.sr.clear_flag(Status::Carry);
-        }
-
-        let rot_val = (*val >> 1) | (*val << 7);
-        cpu::load_u8_memory(&mut self.registers.sr, val, rot_val);
+        cpu::rotate_right(&mut self.registers.sr, val);
     }
 
     pub fn return_from_interrupt(&mut self) {
-        self.registers.sr = StatusRegister(self.bus.read_byte(self.registers.sp as u32));
-        self.registers.pc = self.bus.read_word(1 + self.registers.sp as u32);
-        self.registers.sp += 3;
+        let status = self.pull();
+        self.registers.sr = Processor::<T>::status_from_stack(status);
+        let low = self.pull() as u16;
+        let high = self.pull() as u16;
+        self.registers.pc = (high << 8) | low;
     }
 
     pub fn return_from_subroutine(&mut self) {
-        self.registers.pc = self.bus.read_word(self.registers.sp as u32);
-        self.registers.sp += 2;
+        let low = self.pull() as u16;
+        let high = self.pull() as u16;
+        self.registers.pc = ((high << 8) | low).wrapping_add(1);
     }
 
+    /* SBC is ADC of the complement. NMOS decimal mode keeps every flag from the
+     * binary subtraction and only corrects the result */
     pub fn subtract_with_carry(&mut self, val: u8) {
-        let mut set = Status::Mix(0);
-        let mut clear = Status::Carry | Status::Overflow;
-
-        let diff = self.registers.a - val - self.registers.sr.flag_value(Status::Carry);
-
-        if self.registers.sr.contains(Status::Decimal) {
-            let mut nibble_low = diff & 0xf;
-            let mut nibble_high = diff >> 4;
-
-            if nibble_low > 9 {
-                nibble_low -= 10;
-                nibble_high += 1;
-            }
-            if nibble_high > 9 {
-                nibble_high -= 10;
-            }
-
-            self.registers.a = (nibble_high << 4) | nibble_low;
+        if !self.decimal_mode() {
+            return self.add_with_carry_binary(!val);
         }
 
-        if diff < self.registers.a || diff < val {
-            set |= Status::Carry;
-            clear ^= Status::Carry;
+        let accumulator = self.registers.a;
+        let carry = self.registers.sr.flag_value(Status::Carry);
+        self.add_with_carry_binary(!val);
+
+        let accumulator = accumulator as i16;
+        let val = val as i16;
+        let mut low = (accumulator & 0x0f) - (val & 0x0f) - (1 - carry as i16);
+        let mut high = (accumulator >> 4) - (val >> 4);
+        if low < 0 {
+            low -= 0x06;
+            high -= 1;
         }
-        if diff & 0x80 != ((self.registers.a & 0x80) | (val & 0x80)) {
-            set |= Status::Overflow;
-            clear ^= Status::Overflow;
+        if high < 0 {
+            high -= 0x06;
         }
 
-        cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a, diff);
-
-        self.registers.sr.set_flag(set);
-        self.registers.sr.clear_flag(clear);
+        self.registers.a = (((high << 4) | (low & 0x0f)) & 0xff) as u8;
     }
 
     pub fn store_accumulator(&mut self, addr: u16) {
-        self.bus.write_byte(addr as u32, self.registers.a);
+        self.bus.write_byte(addr, self.registers.a);
     }
 
     pub fn store_x(&mut self, addr: u16) {
-        self.bus.write_byte(addr as u32, self.registers.x);
+        self.bus.write_byte(addr, self.registers.x);
    }
 
     pub fn store_y(&mut self, addr: u16) {
-        self.bus.write_byte(addr as u32, self.registers.y);
+        self.bus.write_byte(addr, self.registers.y);
     }
 
     #[inline(always)]
@@ -701,9 +1027,10 @@ where
         cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a, self.registers.x);
     }
 
+    /* TXS is the only transfer that leaves the flags alone */
     #[inline(always)]
     pub fn load_x_in_stackpointer(&mut self) {
-        cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.sp, self.registers.x);
+        self.registers.sp = self.registers.x;
     }
 
     #[inline(always)]
@@ -711,3 +1038,8 @@ where
         cpu::load_u8_memory(&mut self.registers.sr, &mut self.registers.a, self.registers.y);
     }
 }
+
+mod trace;
+
+#[cfg(test)]
+mod tests;
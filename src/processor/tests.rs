@@ -0,0 +1,795 @@
+/* Imports{{{1*/
+use std::{
+    env,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use super::{CpuVariant, ProcState, Processor, UnstableBehavior, NMI_VECTOR, IRQ_VECTOR};
+use crate::{
+    apu::DMC_STALL_CYCLES,
+    cartridge::test_image::TestImage,
+    cpu,
+    memory::{Bus, Endianness, Readable, Writable},
+    opcode::is_documented,
+    register::{Status, StatusRegister},
+};
+//}}}1
+
+/* Flat 64 KB of RAM, the memory model the reference vectors assume */
+struct FlatMemory {
+    ram: Vec<u8>,
+    writes: Vec<(u16, u8)>,
+}
+
+impl Default for FlatMemory {
+    fn default() -> Self {
+        FlatMemory { ram: vec![0; 0x10000], writes: Vec::new() }
+    }
+}
+
+impl Readable<u16> for FlatMemory {
+    fn has_endian(&self) -> Endianness {
+        Endianness::Little
+    }
+
+    fn read_byte(&mut self, addr: u16) -> u8 {
+        self.ram[addr as usize]
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        self.ram[addr as usize]
+    }
+}
+
+impl Writable<u16> for FlatMemory {
+    fn has_endian(&self) -> Endianness {
+        Endianness::Little
+    }
+
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        self.ram[addr as usize] = byte;
+        self.writes.push((addr, byte));
+    }
+}
+
+fn processor() -> Processor<FlatMemory> {
+    let mut proc = Processor::<FlatMemory>::new();
+    proc.variant = CpuVariant::Nmos6502;
+    proc
+}
+
+/* Helper flags{{{1*/
+#[test]
+fn compare_sets_carry_when_greater() {
+    let mut sr = StatusRegister(0);
+    cpu::compare_u8(&mut sr, 0x40, 0x10);
+    assert!(sr.contains(Status::Carry));
+    assert!(!sr.contains(Status::Zero));
+    assert!(!sr.contains(Status::Negative));
+
+    cpu::compare_u8(&mut sr, 0x10, 0x20);
+    assert!(!sr.contains(Status::Carry));
+    assert!(sr.contains(Status::Negative));
+
+    cpu::compare_u8(&mut sr, 0x80, 0x80);
+    assert!(sr.contains(Status::Carry));
+    assert!(sr.contains(Status::Zero));
+}
+
+#[test]
+fn rotate_right_takes_negative_from_carry_in() {
+    let mut sr = StatusRegister(0);
+    let mut val = 0x01;
+    cpu::rotate_right(&mut sr, &mut val);
+    assert_eq!(val, 0x00);
+    assert!(sr.contains(Status::Carry));
+    assert!(sr.contains(Status::Zero));
+    assert!(!sr.contains(Status::Negative));
+
+    cpu::rotate_right(&mut sr, &mut val);
+    assert_eq!(val, 0x80);
+    assert!(!sr.contains(Status::Carry));
+    assert!(sr.contains(Status::Negative));
+}
+
+#[test]
+fn rotate_left_shifts_carry_in() {
+    let mut sr = StatusRegister(1);
+    let mut val = 0x40;
+    cpu::rotate_left(&mut sr, &mut val);
+    assert_eq!(val, 0x81);
+    assert!(!sr.contains(Status::Carry));
+    assert!(sr.contains(Status::Negative));
+}
+
+#[test]
+fn processor_helpers_match_the_instructions() {
+    let mut proc = processor();
+    proc.registers.sr = StatusRegister(0x01);
+    let mut val = 0x80;
+    proc.rotate_left(&mut val);
+    assert_eq!(val, 0x01, "the carry goes in, bit 7 goes out to C");
+    assert!(proc.registers.sr.contains(Status::Carry));
+
+    proc.rotate_right(&mut val);
+    assert_eq!(val, 0x80);
+    assert!(proc.registers.sr.contains(Status::Carry));
+
+    proc.registers.x = 0x00;
+    proc.registers.sr = StatusRegister(0x80);
+    proc.load_x_in_stackpointer();
+    assert_eq!(proc.registers.sp, 0x00);
+    assert_eq!(proc.registers.sr.0, 0x80);
+}
+
+#[test]
+fn shift_left_to_zero_sets_zero_and_carry() {
+    let mut sr = StatusRegister(0);
+    let mut val = 0x80;
+    cpu::shift_left(&mut sr, &mut val);
+    assert_eq!(val, 0);
+    assert!(sr.contains(Status::Carry));
+    assert!(sr.contains(Status::Zero));
+}
+
+#[test]
+fn transfers_update_flags_except_txs() {
+    let mut proc = processor();
+    proc.registers.a = 0x80;
+    proc.registers.x = 0x00;
+    proc.registers.sr = StatusRegister(0x24);
+    proc.bus.ram[0..3].copy_from_slice(&[0xaa, 0x8a, 0x9a]); /* TAX, TXA, TXS */
+
+    proc.step();
+    assert_eq!(proc.registers.x, 0x80);
+    assert!(proc.registers.sr.contains(Status::Negative));
+
+    proc.registers.x = 0;
+    proc.step();
+    assert_eq!(proc.registers.a, 0);
+    assert!(proc.registers.sr.contains(Status::Zero));
+
+    let sr = proc.registers.sr.0;
+    proc.step();
+    assert_eq!(proc.registers.sp, 0);
+    assert_eq!(proc.registers.sr.0, sr);
+}
+
+/* Addressing modes{{{1*/
+fn load_program(proc: &mut Processor<FlatMemory>, program: &[u8]) {
+    proc.registers.pc = 0x0200;
+    proc.bus.ram[0x0200..(0x0200 + program.len())].copy_from_slice(program);
+}
+
+#[test]
+fn jmp_indirect_wraps_within_page() {
+    let mut proc = processor();
+    load_program(&mut proc, &[0x6c, 0xff, 0x10]);    /* JMP ($10FF) */
+    proc.bus.ram[0x10ff] = 0x34;
+    proc.bus.ram[0x1000] = 0x12;
+    proc.bus.ram[0x1100] = 0x56;
+
+    proc.step();
+    assert_eq!(proc.registers.pc, 0x1234);
+}
+
+#[test]
+fn zero_page_indexed_wraps() {
+    let mut proc = processor();
+    load_program(&mut proc, &[0xb5, 0xff, 0xb6, 0x80]);  /* LDA $FF,X; LDX $80,Y */
+    proc.registers.x = 0x02;
+    proc.registers.y = 0x90;
+    proc.bus.ram[0x0001] = 0x42;
+    proc.bus.ram[0x0101] = 0x99;
+    proc.bus.ram[0x0010] = 0x24;
+
+    proc.step();
+    assert_eq!(proc.registers.a, 0x42);
+    proc.step();
+    assert_eq!(proc.registers.x, 0x24);
+}
+
+#[test]
+fn indexed_indirect_pointer_wraps() {
+    let mut proc = processor();
+    load_program(&mut proc, &[0xa1, 0xfe]);  /* LDA ($FE,X) */
+    proc.registers.x = 0x01;
+    proc.bus.ram[0x00ff] = 0x00;
+    proc.bus.ram[0x0000] = 0x03;
+    proc.bus.ram[0x0100] = 0x04;
+    proc.bus.ram[0x0300] = 0x77;
+
+    proc.step();
+    assert_eq!(proc.registers.a, 0x77);
+}
+
+#[test]
+fn indirect_indexed_pointer_wraps_and_carries() {
+    let mut proc = processor();
+    load_program(&mut proc, &[0xb1, 0xff]);  /* LDA ($FF),Y */
+    proc.registers.y = 0x10;
+    proc.bus.ram[0x00ff] = 0xf8;
+    proc.bus.ram[0x0000] = 0x03;
+    proc.bus.ram[0x0100] = 0x05;
+    proc.bus.ram[0x0408] = 0x66;
+
+    assert_eq!(proc.step(), 6);
+    assert_eq!(proc.registers.a, 0x66);
+}
+
+#[test]
+fn absolute_indexed_carries_into_next_page() {
+    let mut proc = processor();
+    load_program(&mut proc, &[0xbd, 0xff, 0x12, 0x9d, 0xff, 0x12]);  /* LDA $12FF,X; STA $12FF,X */
+    proc.registers.x = 0x01;
+    proc.bus.ram[0x1300] = 0x55;
+    proc.bus.ram[0x1200] = 0xaa;
+
+    assert_eq!(proc.step(), 5);
+    assert_eq!(proc.registers.a, 0x55);
+
+    proc.registers.a = 0x11;
+    assert_eq!(proc.step(), 5);
+    assert_eq!(proc.bus.ram[0x1300], 0x11);
+}
+
+#[test]
+fn read_modify_write_writes_twice() {
+    let mut proc = processor();
+    load_program(&mut proc, &[0xee, 0x00, 0x40, 0x0a]);  /* INC $4000; ASL A */
+    proc.bus.ram[0x4000] = 0x7f;
+    proc.registers.a = 0x81;
+
+    assert_eq!(proc.step(), 6);
+    assert_eq!(proc.bus.writes, [(0x4000, 0x7f), (0x4000, 0x80)]);
+    assert!(proc.registers.sr.contains(Status::Negative));
+
+    proc.step();
+    assert_eq!(proc.registers.a, 0x02);
+    assert!(proc.registers.sr.contains(Status::Carry));
+    assert_eq!(proc.bus.writes.len(), 2);
+}
+
+/* Interrupts{{{1*/
+const NMI_HANDLER: u16 = 0x3000;
+const IRQ_HANDLER: u16 = 0x4000;
+const RTI: u8 = 0x40;
+const NOP: u8 = 0xea;
+const CLI: u8 = 0x58;
+
+/* Both handlers return straight away */
+fn interrupt_processor(program: &[u8], status: u8) -> Processor<FlatMemory> {
+    let mut proc = processor();
+    load_program(&mut proc, program);
+    proc.registers.sp = 0xfd;
+    proc.registers.sr = StatusRegister(status);
+    proc.bus.write_word(NMI_VECTOR, NMI_HANDLER);
+    proc.bus.write_word(IRQ_VECTOR, IRQ_HANDLER);
+    proc.bus.ram[NMI_HANDLER as usize] = RTI;
+    proc.bus.ram[IRQ_HANDLER as usize] = RTI;
+    proc
+}
+
+#[test]
+fn nmi_is_edge_triggered() {
+    let mut proc = interrupt_processor(&[NOP, NOP, NOP], 0x24);
+
+    proc.set_nmi(true);
+    assert_eq!(proc.step(), 7);
+    assert_eq!(proc.registers.pc, NMI_HANDLER);
+    proc.step();
+    assert_eq!(proc.registers.pc, 0x0200);
+
+    /* Holding the line low does not raise another one */
+    proc.set_nmi(true);
+    proc.step();
+    assert_eq!(proc.registers.pc, 0x0201);
+
+    proc.set_nmi(false);
+    proc.set_nmi(true);
+    proc.step();
+    assert_eq!(proc.registers.pc, NMI_HANDLER);
+}
+
+#[test]
+fn irq_is_level_triggered() {
+    let mut proc = interrupt_processor(&[NOP, NOP, NOP], 0x20);
+
+    proc.set_irq(true);
+    assert_eq!(proc.step(), 7);
+    assert_eq!(proc.registers.pc, IRQ_HANDLER);
+    assert!(proc.registers.sr.contains(Status::InterruptDisable));
+
+    /* RTI clears I again while the line is still held, so the IRQ is taken again */
+    proc.step();
+    assert_eq!(proc.registers.pc, 0x0200);
+    proc.step();
+    assert_eq!(proc.registers.pc, IRQ_HANDLER);
+
+    proc.step();
+    proc.set_irq(false);
+    proc.step();
+    assert_eq!(proc.registers.pc, 0x0201);
+}
+
+#[test]
+fn interrupt_disable_masks_irq_one_instruction_late() {
+    let mut proc = interrupt_processor(&[NOP, CLI, NOP, NOP], 0x24);
+
+    proc.set_irq(true);
+    proc.step();
+    assert_eq!(proc.registers.pc, 0x0201);
+
+    /* The poll during CLI still sees I set, so one more instruction runs */
+    proc.step();
+    proc.step();
+    assert_eq!(proc.registers.pc, 0x0203);
+    proc.step();
+    assert_eq!(proc.registers.pc, IRQ_HANDLER);
+}
+
+#[test]
+fn only_brk_pushes_the_b_flag() {
+    let mut proc = interrupt_processor(&[0x00, 0xff, NOP], 0xc3);
+    proc.step();
+    assert_eq!(proc.registers.pc, IRQ_HANDLER);
+    assert_eq!(&proc.bus.ram[0x01fb..=0x01fd], [0xf3, 0x02, 0x02]);    /* P, then PC + 2 */
+    assert!(!proc.registers.sr.contains(Status::Break));
+
+    let mut proc = interrupt_processor(&[NOP], 0xc3);
+    proc.set_irq(true);
+    proc.step();
+    assert_eq!(&proc.bus.ram[0x01fb..=0x01fd], [0xe3, 0x00, 0x02]);
+
+    let mut proc = interrupt_processor(&[NOP], 0xc3);
+    proc.set_nmi(true);
+    proc.step();
+    assert_eq!(&proc.bus.ram[0x01fb..=0x01fd], [0xe3, 0x00, 0x02]);
+}
+
+/* A console running `program` from $8000 out of NROM, with NMI pointing at $9000 and IRQ
+ * at $A000 */
+fn nrom_processor(program: &[u8]) -> Processor<Bus> {
+    let image = TestImage::new(0, 2, 1)
+        .with_prg_fill(|_| NOP)
+        .with_prg(0, program)
+        .with_vectors(0x9000, 0x8000, 0xa000);
+
+    let mut proc = Processor::<Bus>::new();
+    proc.bus.insert_cartridge(image.cartridge()).unwrap();
+    proc.power_on();
+    proc
+}
+
+/* NROM with BRK at $8000, NMI at $9000 and IRQ at $A000, and the PPU set to raise NMI
+ * at vblank. The vblank flag goes up on the `dots`th PPU dot from now. */
+fn brk_before_vblank(dots: u16) -> Processor<Bus> {
+    let mut proc = nrom_processor(&[0x00]);
+
+    /* Vblank starts on dot 1 of line 241 */
+    proc.bus.ppu.ctrl = 0x80;
+    proc.bus.ppu.scanline = 240;
+    proc.bus.ppu.dot = 343 - dots;
+    proc
+}
+
+#[test]
+fn nmi_hijacks_brk_before_the_vector_fetch() {
+    let mut proc = brk_before_vblank(6);
+    proc.tick();
+    assert_eq!(proc.registers.pc, 0x9000);
+    assert_eq!(proc.bus.peek(0x0100 | proc.registers.sp.wrapping_add(1) as u16) & 0x10, 0x10);
+
+    /* The NMI was used up by the hijack */
+    proc.tick();
+    assert_eq!(proc.registers.pc, 0x9001);
+}
+
+#[test]
+fn nmi_after_the_vector_fetch_waits_for_brk() {
+    let mut proc = brk_before_vblank(16);
+    proc.tick();
+    assert_eq!(proc.registers.pc, 0xa000);
+    proc.tick();
+    assert_eq!(proc.registers.pc, 0x9000);
+}
+
+/* OAM DMA{{{1*/
+/* STA $4014 takes 4 cycles, then the CPU halts for the copy */
+fn oam_dma_from_page_2(cycles: u64) -> (Processor<Bus>, u32) {
+    let mut proc = nrom_processor(&[0x8d, 0x14, 0x40]);
+    proc.registers.a = 0x02;
+    for i in 0..0x100 {
+        proc.bus.write_byte(0x0200 + i, i as u8 ^ 0x5a);
+    }
+
+    proc.cycles = cycles;
+    let taken = proc.tick();
+    (proc, taken)
+}
+
+#[test]
+fn oam_dma_copies_a_page_in_513_cycles_from_an_even_cycle() {
+    let (proc, taken) = oam_dma_from_page_2(0);
+    assert_eq!(taken, 4 + 513);
+    assert_eq!(proc.cycles, 4 + 513);
+    assert_eq!(proc.bus.ppu.oam[0x00], 0x5a);
+    assert_eq!(proc.bus.ppu.oam[0xff], 0xa5);
+    assert_eq!(proc.registers.pc, 0x8003);
+}
+
+#[test]
+fn oam_dma_takes_514_cycles_from_an_odd_cycle() {
+    let (proc, taken) = oam_dma_from_page_2(1);
+    assert_eq!(taken, 4 + 514);
+    assert_eq!(proc.cycles, 1 + 4 + 514);
+}
+
+#[test]
+fn oam_dma_keeps_the_ppu_running() {
+    let (proc, taken) = oam_dma_from_page_2(0);
+    let ppu = &proc.bus.ppu;
+    assert_eq!(ppu.scanline as u32 * 341 + ppu.dot as u32, 3 * taken);
+}
+
+#[test]
+fn dmc_fetch_stalls_the_cpu() {
+    let mut proc = nrom_processor(&[NOP, NOP]);
+    proc.bus.write_byte(0x4013, 0x00);      /* A one-byte sample from $C000 */
+    proc.bus.write_byte(0x4015, 0x10);
+
+    assert_eq!(proc.tick(), 2 + DMC_STALL_CYCLES);
+    assert_eq!(proc.registers.pc, 0x8001);
+    assert_eq!(proc.bus.apu.dmc.fetch_address(), None);
+
+    /* The byte is buffered, so the next instruction runs at full speed */
+    assert_eq!(proc.tick(), 2);
+}
+
+/* Decimal mode{{{1*/
+/* Runs one ADC or SBC immediate with D set, returning A and P */
+fn with_decimal_flag(variant: CpuVariant, opcode: u8, a: u8, operand: u8, carry: bool) -> (u8, StatusRegister) {
+    let mut proc = processor();
+    proc.variant = variant;
+    load_program(&mut proc, &[opcode, operand]);
+    proc.registers.a = a;
+    proc.registers.sr = StatusRegister(if carry { 0x29 } else { 0x28 });
+    proc.step();
+    (proc.registers.a, proc.registers.sr)
+}
+
+const ADC: u8 = 0x69;
+const SBC: u8 = 0xe9;
+
+#[test]
+fn ricoh_ignores_the_decimal_flag() {
+    let (a, sr) = with_decimal_flag(CpuVariant::Ricoh2A03, ADC, 0x09, 0x01, false);
+    assert_eq!(a, 0x0a);
+    assert!(sr.contains(Status::Decimal), "D can still be set and read back");
+
+    let (a, sr) = with_decimal_flag(CpuVariant::Ricoh2A03, ADC, 0x99, 0x01, false);
+    assert_eq!(a, 0x9a);
+    assert!(!sr.contains(Status::Carry));
+
+    let (a, _) = with_decimal_flag(CpuVariant::Ricoh2A03, SBC, 0x10, 0x01, true);
+    assert_eq!(a, 0x0f);
+}
+
+#[test]
+fn nmos_adc_adds_bcd() {
+    for (a, operand, carry, result, c) in [
+        (0x09, 0x01, false, 0x10, false),
+        (0x58, 0x46, true, 0x05, true),
+        (0x12, 0x34, false, 0x46, false),
+    ] {
+        let (sum, sr) = with_decimal_flag(CpuVariant::Nmos6502, ADC, a, operand, carry);
+        assert_eq!(sum, result, "{:02x} + {:02x}", a, operand);
+        assert_eq!(sr.contains(Status::Carry), c, "{:02x} + {:02x}", a, operand);
+    }
+}
+
+#[test]
+fn nmos_adc_takes_z_from_the_binary_sum_and_n_v_before_the_high_adjust() {
+    /* $99 + $01 is $00 with carry, but the binary sum $9A leaves Z clear and the
+     * intermediate $A0 sets N */
+    let (a, sr) = with_decimal_flag(CpuVariant::Nmos6502, ADC, 0x99, 0x01, false);
+    assert_eq!(a, 0x00);
+    assert!(sr.contains(Status::Carry));
+    assert!(!sr.contains(Status::Zero));
+    assert!(sr.contains(Status::Negative));
+    assert!(!sr.contains(Status::Overflow));
+
+    /* $79 + $10 = $89: two positive operands give a negative intermediate */
+    let (a, sr) = with_decimal_flag(CpuVariant::Nmos6502, ADC, 0x79, 0x10, false);
+    assert_eq!(a, 0x89);
+    assert!(sr.contains(Status::Overflow));
+    assert!(sr.contains(Status::Negative));
+    assert!(!sr.contains(Status::Carry));
+}
+
+#[test]
+fn nmos_sbc_subtracts_bcd_with_binary_flags() {
+    let (a, sr) = with_decimal_flag(CpuVariant::Nmos6502, SBC, 0x46, 0x12, true);
+    assert_eq!(a, 0x34);
+    assert!(sr.contains(Status::Carry));
+
+    let (a, sr) = with_decimal_flag(CpuVariant::Nmos6502, SBC, 0x40, 0x13, true);
+    assert_eq!(a, 0x27);
+    assert!(sr.contains(Status::Carry));
+
+    /* $12 - $21 borrows to $91; N comes from the binary $F1 */
+    let (a, sr) = with_decimal_flag(CpuVariant::Nmos6502, SBC, 0x12, 0x21, true);
+    assert_eq!(a, 0x91);
+    assert!(!sr.contains(Status::Carry));
+    assert!(sr.contains(Status::Negative));
+
+    /* $20 - $00 - borrow is $19, and Z stays clear like the binary $1F */
+    let (a, sr) = with_decimal_flag(CpuVariant::Nmos6502, SBC, 0x20, 0x00, false);
+    assert_eq!(a, 0x19);
+    assert!(!sr.contains(Status::Zero));
+    assert!(sr.contains(Status::Carry));
+}
+
+/* Unofficial opcodes{{{1*/
+#[test]
+fn arr_takes_carry_from_bit_6_and_overflow_from_bits_6_and_5() {
+    /* A, operand and carry in; A, C and V out */
+    for (a, operand, carry, result, c, v) in [
+        (0xff, 0xff, true, 0xff, true, false),
+        (0x60, 0xff, false, 0x30, false, true),
+        (0x80, 0x80, false, 0x40, true, true),
+        (0x01, 0xff, false, 0x00, false, false),
+    ] {
+        let mut proc = processor();
+        load_program(&mut proc, &[0x6b, operand]);      /* ARR #operand */
+        proc.registers.a = a;
+        proc.registers.sr = StatusRegister(if carry { 0x21 } else { 0x20 });
+
+        proc.step();
+        assert_eq!(proc.registers.a, result, "{:02x} & {:02x}", a, operand);
+        assert_eq!(proc.registers.sr.contains(Status::Carry), c, "{:02x} & {:02x}", a, operand);
+        assert_eq!(proc.registers.sr.contains(Status::Overflow), v, "{:02x} & {:02x}", a, operand);
+        assert_eq!(proc.registers.sr.contains(Status::Negative), carry);
+        assert_eq!(proc.registers.sr.contains(Status::Zero), result == 0);
+    }
+}
+
+#[test]
+fn axs_subtracts_from_a_and_x_without_borrow() {
+    let mut proc = processor();
+    load_program(&mut proc, &[0xcb, 0x10, 0xcb, 0x31]);    /* AXS #$10, AXS #$31 */
+    proc.registers.a = 0xf0;
+    proc.registers.x = 0x3c;
+    proc.registers.sr = StatusRegister(0x60);
+
+    proc.step();
+    assert_eq!(proc.registers.x, 0x20);
+    assert!(proc.registers.sr.contains(Status::Carry));
+    assert!(proc.registers.sr.contains(Status::Overflow), "V is left alone");
+
+    proc.registers.x = 0x3c;
+    proc.step();
+    assert_eq!(proc.registers.x, 0xff);
+    assert!(!proc.registers.sr.contains(Status::Carry));
+    assert!(proc.registers.sr.contains(Status::Negative));
+    assert_eq!(proc.registers.a, 0xf0);
+}
+
+#[test]
+fn isc_increments_then_subtracts() {
+    let mut proc = processor();
+    load_program(&mut proc, &[0xe7, 0x10, 0xe7, 0x11]);    /* ISC $10, ISC $11 */
+    proc.bus.ram[0x10] = 0x0f;
+    proc.bus.ram[0x11] = 0x00;
+    proc.registers.a = 0x20;
+    proc.registers.sr = StatusRegister(0x21);
+
+    proc.step();
+    assert_eq!(proc.bus.ram[0x10], 0x10);
+    assert_eq!(proc.registers.a, 0x10);
+    assert!(proc.registers.sr.contains(Status::Carry));
+
+    /* $80 - $01 overflows into a positive result */
+    proc.registers.a = 0x80;
+    proc.step();
+    assert_eq!(proc.bus.ram[0x11], 0x01);
+    assert_eq!(proc.registers.a, 0x7f);
+    assert!(proc.registers.sr.contains(Status::Overflow));
+    assert!(proc.registers.sr.contains(Status::Carry));
+    assert!(!proc.registers.sr.contains(Status::Negative));
+}
+
+#[test]
+fn rra_rotates_through_carry_then_adds() {
+    let mut proc = processor();
+    load_program(&mut proc, &[0x67, 0x10, 0x67, 0x11]);    /* RRA $10, RRA $11 */
+    proc.bus.ram[0x10] = 0x03;
+    proc.bus.ram[0x11] = 0x02;
+    proc.registers.a = 0x70;
+    proc.registers.sr = StatusRegister(0x21);
+
+    /* $03 rotates to $81 with C set, then $70 + $81 + 1 */
+    proc.step();
+    assert_eq!(proc.bus.ram[0x10], 0x81);
+    assert_eq!(proc.registers.a, 0xf2);
+    assert!(!proc.registers.sr.contains(Status::Carry));
+    assert!(!proc.registers.sr.contains(Status::Overflow));
+    assert!(proc.registers.sr.contains(Status::Negative));
+
+    /* $02 rotates to $01 with C clear, then $7F + $01 overflows */
+    proc.registers.a = 0x7f;
+    proc.step();
+    assert_eq!(proc.bus.ram[0x11], 0x01);
+    assert_eq!(proc.registers.a, 0x80);
+    assert!(!proc.registers.sr.contains(Status::Carry));
+    assert!(proc.registers.sr.contains(Status::Overflow));
+}
+
+#[test]
+fn jam_halts_until_reset() {
+    let mut proc = processor();
+    load_program(&mut proc, &[0x02, NOP]);
+    proc.bus.write_word(0xfffc, 0x0201);
+
+    proc.step();
+    assert!(matches!(proc.state, ProcState::Halted));
+    assert_eq!(proc.registers.pc, 0x0200);
+
+    /* Each step burns a cycle without fetching */
+    let cycles = proc.cycles;
+    assert_eq!(proc.step(), 1);
+    assert_eq!(proc.cycles, cycles + 1);
+    assert_eq!(proc.registers.pc, 0x0200);
+
+    proc.reset();
+    proc.step();
+    assert_eq!(proc.registers.pc, 0x0202);
+}
+
+/* SHY $12F0,X with X = $20 crosses into page $13, so the stored value also picks the page */
+fn shy_across_a_page(unstable: UnstableBehavior) -> Processor<FlatMemory> {
+    let mut proc = processor();
+    proc.unstable = unstable;
+    load_program(&mut proc, &[0x9c, 0xf0, 0x12]);
+    proc.registers.x = 0x20;
+    proc.registers.y = 0x0f;
+    proc.step();
+    proc
+}
+
+#[test]
+fn unstable_setting_picks_what_sha_shx_and_shy_do() {
+    /* Y & ($12 + 1) = $03 lands on page $03 */
+    let proc = shy_across_a_page(UnstableBehavior::Emulate { magic: 0xee });
+    assert_eq!(proc.bus.writes, [(0x0310, 0x03)]);
+    assert_eq!(proc.registers.pc, 0x0203);
+
+    let proc = shy_across_a_page(UnstableBehavior::Nop);
+    assert!(proc.bus.writes.is_empty());
+    assert_eq!(proc.registers.pc, 0x0203);
+
+    let proc = shy_across_a_page(UnstableBehavior::Jam);
+    assert!(proc.bus.writes.is_empty());
+    assert!(matches!(proc.state, ProcState::Halted));
+    assert_eq!(proc.registers.pc, 0x0200);
+
+    /* SHX $1200,Y and SHA $1200,Y stay on the page */
+    for (program, stored) in [([0x9e, 0x00, 0x12], 0x13), ([0x9f, 0x00, 0x12], 0x03)] {
+        let mut proc = processor();
+        load_program(&mut proc, &program);
+        proc.registers.a = 0x0f;
+        proc.registers.x = 0xff;
+        proc.registers.y = 0x05;
+        proc.step();
+        assert_eq!(proc.bus.writes, [(0x1205, stored)]);
+
+        let mut proc = processor();
+        proc.unstable = UnstableBehavior::Nop;
+        load_program(&mut proc, &program);
+        proc.step();
+        assert!(proc.bus.writes.is_empty());
+    }
+}
+
+/* SingleStepTests{{{1*/
+/* The vectors (github.com/SingleStepTests/65x02, directory 6502/v1) are one JSON file
+ * per opcode; they are read from tests/6502/v1, where tests/6502/vendor.sh keeps the
+ * first CASES_PER_OPCODE of each, or the directory in SINGLE_STEP_TESTS */
+fn vector_dir() -> PathBuf {
+    match env::var_os("SINGLE_STEP_TESTS") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/6502/v1"),
+    }
+}
+
+/* Upstream has 10000 per opcode; a full copy in SINGLE_STEP_TESTS is cut down to match */
+const CASES_PER_OPCODE: usize = 100;
+
+fn field(state: &Value, name: &str) -> u64 {
+    state[name].as_u64().unwrap_or_else(|| panic!("vector is missing `{}`", name))
+}
+
+fn load_state(proc: &mut Processor<FlatMemory>, state: &Value) {
+    proc.registers.pc = field(state, "pc") as u16;
+    proc.registers.sp = field(state, "s") as u8;
+    proc.registers.a = field(state, "a") as u8;
+    proc.registers.x = field(state, "x") as u8;
+    proc.registers.y = field(state, "y") as u8;
+    proc.registers.sr = StatusRegister(field(state, "p") as u8);
+
+    for cell in state["ram"].as_array().into_iter().flatten() {
+        proc.bus.ram[cell[0].as_u64().unwrap() as usize] = cell[1].as_u64().unwrap() as u8;
+    }
+}
+
+/* Returns a description of the first mismatch. Bits 4 and 5 of P have no storage
+ * in the chip, so they are not compared. */
+fn compare_state(proc: &Processor<FlatMemory>, state: &Value, cycles: u32, expected_cycles: usize) -> Option<String> {
+    let registers = [
+        ("pc", proc.registers.pc as u64, field(state, "pc")),
+        ("s", proc.registers.sp as u64, field(state, "s")),
+        ("a", proc.registers.a as u64, field(state, "a")),
+        ("x", proc.registers.x as u64, field(state, "x")),
+        ("y", proc.registers.y as u64, field(state, "y")),
+        ("p", proc.registers.sr.0 as u64 & 0xcf, field(state, "p") & 0xcf),
+        ("cycles", cycles as u64, expected_cycles as u64),
+    ];
+
+    for (name, found, expected) in registers {
+        if found != expected {
+            return Some(format!("{}: expected {:#x}, found {:#x}", name, expected, found));
+        }
+    }
+
+    for cell in state["ram"].as_array().into_iter().flatten() {
+        let addr = cell[0].as_u64().unwrap() as usize;
+        let expected = cell[1].as_u64().unwrap() as u8;
+        if proc.bus.ram[addr] != expected {
+            return Some(format!("ram[{:#06x}]: expected {:#04x}, found {:#04x}", addr, expected, proc.bus.ram[addr]));
+        }
+    }
+
+    None
+}
+
+/* Runs the cases in `path`, returning the first mismatch. One report per
+ * opcode is enough to find the bug. */
+fn run_vectors(byte: u8, path: &Path) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let cases: Value = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    for case in cases.as_array().into_iter().flatten().take(CASES_PER_OPCODE) {
+        let mut proc = processor();
+        load_state(&mut proc, &case["initial"]);
+        let cycles = proc.step();
+
+        let expected_cycles = case["cycles"].as_array().map_or(0, |c| c.len());
+        if let Some(mismatch) = compare_state(&proc, &case["final"], cycles, expected_cycles) {
+            return Err(format!("{:02x} \"{}\": {}", byte, case["name"].as_str().unwrap_or("?"), mismatch));
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn single_step_tests() {
+    let dir = vector_dir();
+    if !dir.is_dir() {
+        /* Same rule as the nestest comparison: CI must not pass without the vectors */
+        let message = format!("{} not found, see tests/6502/README.md", dir.display());
+        assert!(env::var_os("CI").is_none(), "{}", message);
+        eprintln!("warning: skipping single_step_tests: {}", message);
+        return;
+    }
+
+    let failures: Vec<String> = (0..=0xff_u8)
+        .filter(|&byte| is_documented(byte))
+        .filter_map(|byte| run_vectors(byte, &dir.join(format!("{:02x}.json", byte))).err())
+        .collect();
+
+    assert!(failures.is_empty(), "{} opcodes failed:\n{}", failures.len(), failures.join("\n"));
+}
@@ -0,0 +1,88 @@
+use std::fmt::Write;
+
+use super::Processor;
+use crate::{
+    memory::{Bus, Readable},
+    opcode::{MemAddressMode, Opcode, OPCODES, is_documented},
+};
+
+impl Processor<Bus> {
+    /* The instruction at PC and the machine state before it runs, laid out like
+     * Nintendulator's debug log so traces can be diffed against nestest.log:
+     *   C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+     * Operands show the memory they touch as it is now, read without side effects. */
+    pub fn trace(&self) -> String {
+        let pc = self.registers.pc;
+        let op = self.bus.peek(pc);
+        let operand_len = OPCODES[op as usize].map_or(0, |(_, mode)| mode.operand_len());
+
+        let mut bytes = format!("{:02X}", op);
+        for i in 1..=operand_len {
+            let _ = write!(bytes, " {:02X}", self.bus.peek(pc.wrapping_add(i)));
+        }
+
+        let star = if is_documented(op) { ' ' } else { '*' };
+        let r = &self.registers;
+        format!("{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+            pc, bytes, star, self.disassemble(pc), r.a, r.x, r.y, r.sr.0, r.sp,
+            self.bus.ppu.scanline, self.bus.ppu.dot, self.cycles)
+    }
+
+    fn peek_word_in_page(&self, addr: u16) -> u16 {
+        let high = (addr & 0xff00) | (addr.wrapping_add(1) & 0x00ff);
+        (self.bus.peek(high) as u16) << 8 | self.bus.peek(addr) as u16
+    }
+
+    fn disassemble(&self, pc: u16) -> String {
+        let (opcode, mode) = match OPCODES[self.bus.peek(pc) as usize] {
+            Some(inst) => inst,
+            None => return "???".to_string(),
+        };
+
+        let name = match opcode {
+            Opcode::ISC => "ISB".to_string(),
+            _ => format!("{:?}", opcode),
+        };
+        let byte = self.bus.peek(pc.wrapping_add(1));
+        let word = self.bus.peek_word(pc.wrapping_add(1));
+        let (x, y) = (self.registers.x, self.registers.y);
+
+        let operand = match mode {
+            MemAddressMode::Implied => String::new(),
+            MemAddressMode::Accumulator => "A".to_string(),
+            MemAddressMode::Immediate => format!("#${:02X}", byte),
+            MemAddressMode::Relative => {
+                let target = pc.wrapping_add(2).wrapping_add(byte as i8 as u16);
+                format!("${:04X}", target)
+            },
+            MemAddressMode::ZeroPage => format!("${:02X} = {:02X}", byte, self.bus.peek(byte as u16)),
+            MemAddressMode::ZeroPageIndexedX | MemAddressMode::ZeroPageIndexedY => {
+                let (index, reg) = if let MemAddressMode::ZeroPageIndexedX = mode { (x, 'X') } else { (y, 'Y') };
+                let addr = byte.wrapping_add(index);
+                format!("${:02X},{} @ {:02X} = {:02X}", byte, reg, addr, self.bus.peek(addr as u16))
+            },
+            MemAddressMode::Absolute => match opcode {
+                Opcode::JMP | Opcode::JSR => format!("${:04X}", word),
+                _ => format!("${:04X} = {:02X}", word, self.bus.peek(word)),
+            },
+            MemAddressMode::AbsoluteIndexedX | MemAddressMode::AbsoluteIndexedY => {
+                let (index, reg) = if let MemAddressMode::AbsoluteIndexedX = mode { (x, 'X') } else { (y, 'Y') };
+                let addr = word.wrapping_add(index as u16);
+                format!("${:04X},{} @ {:04X} = {:02X}", word, reg, addr, self.bus.peek(addr))
+            },
+            MemAddressMode::Indirect => format!("(${:04X}) = {:04X}", word, self.peek_word_in_page(word)),
+            MemAddressMode::IndirectIndexedX => {
+                let pointer = byte.wrapping_add(x);
+                let addr = self.peek_word_in_page(pointer as u16);
+                format!("(${:02X},X) @ {:02X} = {:04X} = {:02X}", byte, pointer, addr, self.bus.peek(addr))
+            },
+            MemAddressMode::IndirectIndexedY => {
+                let base = self.peek_word_in_page(byte as u16);
+                let addr = base.wrapping_add(y as u16);
+                format!("(${:02X}),Y = {:04X} @ {:04X} = {:02X}", byte, base, addr, self.bus.peek(addr))
+            },
+        };
+
+        if operand.is_empty() { name } else { format!("{} {}", name, operand) }
+    }
+}
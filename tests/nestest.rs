@@ -0,0 +1,63 @@
+use std::{
+    env,
+    fs,
+    path::PathBuf,
+};
+
+use nes::{
+    emulator::Emulator,
+    memory::Readable,
+};
+
+const AUTOMATION_START: u16 = 0xc000;
+const CONTEXT_LINES: usize = 5;
+
+/* Result bytes: nestest leaves the number of the first failing official test in $02
+ * and of the first failing unofficial one in $03 */
+const OFFICIAL_RESULT: u16 = 0x02;
+const UNOFFICIAL_RESULT: u16 = 0x03;
+
+fn nestest_dir() -> PathBuf {
+    match env::var_os("NESTEST_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/nestest"),
+    }
+}
+
+#[test]
+fn nestest_matches_golden_log() {
+    let dir = nestest_dir();
+    let (rom, log) = (dir.join("nestest.nes"), dir.join("nestest.log"));
+    if !rom.exists() || !log.exists() {
+        /* CI services set CI, and a build there must not pass without the comparison */
+        let message = format!("nestest.nes and nestest.log not found in {}, see tests/nestest/README.md", dir.display());
+        assert!(env::var_os("CI").is_none(), "{}", message);
+        eprintln!("warning: skipping nestest: {}", message);
+        return;
+    }
+
+    let log = fs::read_to_string(&log).expect("nestest.log is readable");
+    let expected: Vec<&str> = log.lines().map(str::trim_end).filter(|l| !l.is_empty()).collect();
+
+    let mut emulator = Emulator::new();
+    emulator.load_rom(&rom).expect("nestest.nes loads");
+    emulator.processor.registers.pc = AUTOMATION_START;
+
+    for (line, want) in expected.iter().enumerate() {
+        let got = emulator.processor.trace();
+        if got != *want {
+            let mut report = format!("trace diverges from nestest.log at line {}\n", line + 1);
+            for (n, previous) in expected.iter().enumerate().take(line).skip(line.saturating_sub(CONTEXT_LINES)) {
+                report += &format!("  {:5} {}\n", n + 1, previous);
+            }
+            report += &format!("- {:5} {}\n+ {:5} {}\n", line + 1, want, line + 1, got);
+            panic!("{}", report);
+        }
+
+        emulator.processor.tick();
+    }
+
+    let bus = &emulator.processor.bus;
+    assert_eq!(bus.peek(OFFICIAL_RESULT), 0, "nestest reports official opcode test ${:02X} failed", bus.peek(OFFICIAL_RESULT));
+    assert_eq!(bus.peek(UNOFFICIAL_RESULT), 0, "nestest reports unofficial opcode test ${:02X} failed", bus.peek(UNOFFICIAL_RESULT));
+}